@@ -0,0 +1,29 @@
+// Reader of the config files: one `key = value` per line, `#` starting a comment, blank lines
+// ignored. Errors name the line, and the file when it was loaded from one.
+
+/// Reads the file at `path` with `parse`.
+pub fn load<T>(path: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<T, String> {
+    let source = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+
+    return parse(&source).map_err(|error| format!("{}: {}", path, error));
+}
+
+/// Calls `entry` with the trimmed key and value of every line, stopping at the first error.
+/// `syntax` describes a line in the error of one without `=`.
+pub fn read_lines(source: &str, syntax: &str, mut entry: impl FnMut(&str, &str) -> Result<(), String>) -> Result<(), String> {
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected `{}`", index + 1, syntax));
+        };
+
+        entry(key.trim(), value.trim()).map_err(|error| format!("line {}: {}", index + 1, error))?;
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use glam::Vec2;
+
+use winit::{
+    event::{
+        ElementState,
+        MouseScrollDelta,
+        WindowEvent,
+    },
+    keyboard::{
+        KeyCode,
+        PhysicalKey,
+    },
+};
+
+pub use winit::event::MouseButton;
+
+use crate::input::action::ActionMap;
+
+pub mod action;
+
+/// Anything that can be bound to an action.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Wheel notches, pressed and released within the frame they were scrolled in.
+    WheelUp,
+    WheelDown,
+}
+
+/// Keyboard and mouse state of the current frame, built from the window events.
+pub struct Input {
+    pub actions: ActionMap,
+
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+
+    /// Cursor position in window pixels, y pointing down.
+    pub cursor_position: Vec2,
+    /// Wheel lines scrolled during the frame, positive away from the user.
+    pub wheel: f32,
+}
+
+impl Input {
+    /// Pixel deltas of touchpads are converted to lines of this height.
+    const PIXELS_PER_LINE: f32 = 40.0;
+
+    pub fn new(actions: ActionMap) -> Self {
+        return Self {
+            actions,
+
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+
+            cursor_position: Vec2::ZERO,
+            wheel: 0.0,
+        };
+    }
+
+    pub fn process_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                event,
+                ..
+            } => {
+                // Key repeats are not new presses
+                if event.repeat {
+                    return;
+                }
+
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    self.process_button(Button::Key(code), event.state);
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button,
+                ..
+            } => {
+                self.process_button(Button::Mouse(*button), *state);
+            }
+            WindowEvent::MouseWheel {
+                delta,
+                ..
+            } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / Self::PIXELS_PER_LINE,
+                };
+
+                self.wheel += lines;
+
+                let button = if lines > 0.0 { Button::WheelUp } else { Button::WheelDown };
+
+                if lines != 0.0 {
+                    self.pressed.insert(button);
+                    self.released.insert(button);
+                }
+            }
+            WindowEvent::CursorMoved {
+                position,
+                ..
+            } => {
+                self.cursor_position = Vec2::new(position.x as f32, position.y as f32);
+            }
+            WindowEvent::Focused(false) => {
+                // Releases never arrive once the window lost the focus
+                self.released.extend(self.held.drain());
+            }
+            _ => {}
+        }
+    }
+
+    fn process_button(&mut self, button: Button, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.held.insert(button) {
+                    self.pressed.insert(button);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+        }
+    }
+
+    /// Forgets the edges and the wheel of the frame, to be called once the frame was handled.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.wheel = 0.0;
+    }
+
+    /// Hides the presses and releases of the frame from the later readers, e.g. for a click the
+    /// interface handled.
+    pub fn consume(&mut self, button: Button) {
+        self.pressed.remove(&button);
+        self.released.remove(&button);
+    }
+
+    /// Whether the button is down.
+    pub fn is_held(&self, button: Button) -> bool {
+        return self.held.contains(&button);
+    }
+
+    /// Whether the button went down during the frame.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        return self.pressed.contains(&button);
+    }
+
+    /// Whether the button went up during the frame.
+    pub fn is_released(&self, button: Button) -> bool {
+        return self.released.contains(&button);
+    }
+
+    /// Whether any button bound to the action is down.
+    pub fn action_held(&self, action: &str) -> bool {
+        return self.actions.buttons(action).iter().any(|button| self.is_held(*button));
+    }
+
+    /// Whether any button bound to the action went down during the frame.
+    pub fn action_pressed(&self, action: &str) -> bool {
+        return self.actions.buttons(action).iter().any(|button| self.is_pressed(*button));
+    }
+
+    /// Whether any button bound to the action went up during the frame.
+    pub fn action_released(&self, action: &str) -> bool {
+        return self.actions.buttons(action).iter().any(|button| self.is_released(*button));
+    }
+
+    /// -1, 0 or 1 depending on which of the two actions is held.
+    pub fn action_axis(&self, negative: &str, positive: &str) -> f32 {
+        return self.action_held(positive) as i32 as f32 - self.action_held(negative) as i32 as f32;
+    }
+}
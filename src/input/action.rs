@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use winit::keyboard::KeyCode;
+
+use crate::config;
+use crate::input::{
+    Button,
+    MouseButton,
+};
+
+/// Bindings shipped with the game, used when the config file can't be read.
+const DEFAULT_BINDINGS: &str = include_str!("../../config/input.cfg");
+
+/// Named actions and the buttons triggering them, so gameplay code never looks at the devices.
+///
+/// The config has one action per line, `name = Button, Button`, and `#` comments. Buttons are
+/// winit key codes (`KeyA`, `Digit1`, `ArrowLeft`, `Space`...), `MouseLeft`, `MouseRight`,
+/// `MouseMiddle`, `WheelUp` and `WheelDown`.
+pub struct ActionMap {
+    bindings: HashMap<String, Vec<Button>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        return Self {
+            bindings: HashMap::new(),
+        };
+    }
+
+    pub fn builtin() -> Self {
+        return Self::parse(DEFAULT_BINDINGS).expect("Invalid default input bindings");
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        return config::load(path, Self::parse);
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut map = Self::new();
+
+        config::read_lines(source, "action = Button, ...", |action, buttons| {
+            if action.is_empty() {
+                return Err("missing action name".to_string());
+            }
+
+            for name in buttons.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let Some(button) = Self::button(name) else {
+                    return Err(format!("unknown button `{}`", name));
+                };
+
+                map.bind(action, button);
+            }
+
+            return Ok(());
+        })?;
+
+        return Ok(map);
+    }
+
+    pub fn bind(&mut self, action: &str, button: Button) {
+        let buttons = self.bindings.entry(action.to_string()).or_default();
+
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    /// Buttons bound to the action, empty for unknown actions.
+    pub fn buttons(&self, action: &str) -> &[Button] {
+        return self.bindings.get(action).map_or(&[], Vec::as_slice);
+    }
+
+    fn button(name: &str) -> Option<Button> {
+        let button = match name {
+            "MouseLeft" => Button::Mouse(MouseButton::Left),
+            "MouseRight" => Button::Mouse(MouseButton::Right),
+            "MouseMiddle" => Button::Mouse(MouseButton::Middle),
+            "WheelUp" => Button::WheelUp,
+            "WheelDown" => Button::WheelDown,
+            _ => Button::Key(Self::key(name)?),
+        };
+
+        return Some(button);
+    }
+
+    fn key(name: &str) -> Option<KeyCode> {
+        const LETTERS: [KeyCode; 26] = [
+            KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+            KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+            KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+            KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+            KeyCode::KeyY, KeyCode::KeyZ,
+        ];
+        const DIGITS: [KeyCode; 10] = [
+            KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+            KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+        ];
+        const FUNCTIONS: [KeyCode; 12] = [
+            KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+            KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+        ];
+
+        if let Some(letter) = name.strip_prefix("Key") {
+            let &[letter] = letter.as_bytes() else {
+                return None;
+            };
+
+            return letter.is_ascii_uppercase().then(|| LETTERS[(letter - b'A') as usize]);
+        }
+
+        if let Some(digit) = name.strip_prefix("Digit") {
+            return digit.parse::<usize>().ok().and_then(|digit| DIGITS.get(digit).copied());
+        }
+
+        if let Some(function) = name.strip_prefix('F').and_then(|function| function.parse::<usize>().ok()) {
+            return function.checked_sub(1).and_then(|function| FUNCTIONS.get(function).copied());
+        }
+
+        let key = match name {
+            "ArrowLeft" => KeyCode::ArrowLeft,
+            "ArrowRight" => KeyCode::ArrowRight,
+            "ArrowUp" => KeyCode::ArrowUp,
+            "ArrowDown" => KeyCode::ArrowDown,
+            "Space" => KeyCode::Space,
+            "Enter" => KeyCode::Enter,
+            "Escape" => KeyCode::Escape,
+            "Tab" => KeyCode::Tab,
+            "Backspace" => KeyCode::Backspace,
+            "Backquote" => KeyCode::Backquote,
+            "Minus" => KeyCode::Minus,
+            "Equal" => KeyCode::Equal,
+            "ShiftLeft" => KeyCode::ShiftLeft,
+            "ShiftRight" => KeyCode::ShiftRight,
+            "ControlLeft" => KeyCode::ControlLeft,
+            "ControlRight" => KeyCode::ControlRight,
+            "AltLeft" => KeyCode::AltLeft,
+            "AltRight" => KeyCode::AltRight,
+            _ => return None,
+        };
+
+        return Some(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::KeyCode;
+
+    use crate::input::{
+        Button,
+        MouseButton,
+    };
+
+    use super::ActionMap;
+
+    #[test]
+    fn parses_valid_lines() {
+        let map = ActionMap::parse("# Comment\n\njump = Space, KeyW  # trailing\nfire=MouseLeft,F12,\nzoom_in = WheelUp\nfire = Digit0\n").unwrap();
+
+        assert_eq!(map.buttons("jump"), [Button::Key(KeyCode::Space), Button::Key(KeyCode::KeyW)]);
+        assert_eq!(map.buttons("fire"), [Button::Mouse(MouseButton::Left), Button::Key(KeyCode::F12), Button::Key(KeyCode::Digit0)]);
+        assert_eq!(map.buttons("zoom_in"), [Button::WheelUp]);
+        assert!(map.buttons("crouch").is_empty());
+
+        ActionMap::builtin();
+    }
+
+    #[test]
+    fn rejects_unknown_buttons() {
+        for (source, error) in [
+            ("jump = Space, KeyAB", "line 1: unknown button `KeyAB`"),
+            ("jump = Space\nfire = F13", "line 2: unknown button `F13`"),
+            ("jump = Digit10", "line 1: unknown button `Digit10`"),
+            ("jump = space", "line 1: unknown button `space`"),
+        ] {
+            assert_eq!(ActionMap::parse(source).err().unwrap(), error);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(ActionMap::parse("jump Space").err().unwrap(), "line 1: expected `action = Button, ...`");
+        assert_eq!(ActionMap::parse("\n = Space").err().unwrap(), "line 2: missing action name");
+    }
+}
@@ -1,21 +1,84 @@
+use glam::Vec2;
+
+use crate::input::Input;
+use crate::logic::debug::DebugDraw;
+use crate::logic::hud::Hud;
 use crate::logic::play::Play;
+use crate::logic::timestep::FixedTimestep;
+use crate::profiler::Profiler;
+use crate::ui::Ui;
 
 pub mod camera;
+pub mod debug;
+pub mod hud;
 pub mod play;
+pub mod timestep;
 
 
 pub struct Logic {
     pub play: Play,
+    /// Shapes drawn over the frame, toggled by the `toggle_debug` action.
+    pub debug: DebugDraw,
+    pub hud: Hud,
+
+    timestep: FixedTimestep,
 }
 
 impl Logic {
+    /// Simulation steps per second.
+    pub const TICK_RATE: u32 = 60;
+
     pub fn new() -> Self {
         return Self {
             play: Play::new(),
+            debug: DebugDraw::new(),
+            hud: Hud::new(),
+
+            timestep: FixedTimestep::new(Self::TICK_RATE),
         };
     }
 
-    pub fn process_mouse_position(&mut self, position: (u32, u32)) {
-        self.play.process_mouse_position(position);
+    /// Runs the simulation steps covering `frame_time` seconds. The input edges are consumed by
+    /// the first step, and kept for a later frame when no step runs. The debug shapes of the frame
+    /// are collected last.
+    pub fn advance(&mut self, frame_time: f32, input: &mut Input) {
+        for _ in 0..self.timestep.advance(frame_time) {
+            self.update(self.timestep.step, input);
+
+            input.end_frame();
+        }
+
+        self.play.interpolation = self.timestep.alpha();
+
+        self.debug.clear();
+        self.play.draw_debug(&mut self.debug);
+    }
+
+    /// Declares the interface of the frame, before the frame is advanced so the clicks it handles
+    /// can be consumed.
+    pub fn process_ui(&mut self, ui: &mut Ui, profiler: &Profiler) {
+        self.hud.build(ui, &mut self.play, &mut self.debug, profiler);
+    }
+
+    /// Advances the game by one step of `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f32, input: &Input) {
+        if input.action_pressed("toggle_debug") {
+            self.debug.enabled = !self.debug.enabled;
+        }
+
+        if input.action_pressed("toggle_editor") {
+            self.hud.editor = !self.hud.editor;
+        }
+
+        if input.action_pressed("toggle_profiler") {
+            self.hud.profiler = !self.hud.profiler;
+        }
+
+        self.play.update(delta_time, input);
+    }
+
+    /// `world_position` is the cursor picked through the camera by the renderer.
+    pub fn process_cursor(&mut self, window_position: Vec2, world_position: Vec2) {
+        self.play.process_cursor(window_position, world_position);
     }
 }
\ No newline at end of file
@@ -2,23 +2,167 @@ use glam::{
     Mat4,
     Vec2,
     Vec3,
+    Vec4,
 };
 
+#[derive(Clone)]
 pub struct Camera {
+    /// World position at the center of the view, before the bounds and the shake are applied.
     pub position: Vec2,
+    /// View pixels per world unit.
+    pub zoom: f32,
+    /// Counter-clockwise rotation of the camera in radians, the world turns the other way.
+    pub rotation: f32,
+
+    /// Position followed by `update`, the camera stays in place when unset.
+    pub target: Option<Vec2>,
+    /// Approximate time in seconds to catch up with the target, 0 snaps to it.
+    pub smooth_time: f32,
+    velocity: Vec2,
+
+    /// Minimum and maximum corners of the world area the view is kept inside.
+    pub bounds: Option<(Vec2, Vec2)>,
+
+    /// Shake amount in `[0, 1]`, the offset grows with its square.
+    trauma: f32,
+    /// Trauma lost per second.
+    pub shake_decay: f32,
+    /// Offset in world units and angle in radians reached at full trauma.
+    pub shake_offset: f32,
+    pub shake_angle: f32,
+    shake_time: f32,
 }
 
 impl Camera {
     pub fn new() -> Self {
         return Self {
             position: Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+
+            target: None,
+            smooth_time: 0.25,
+            velocity: Vec2::ZERO,
+
+            bounds: None,
+
+            trauma: 0.0,
+            shake_decay: 1.5,
+            shake_offset: 12.0,
+            shake_angle: 0.05,
+            shake_time: 0.0,
+        };
+    }
+
+    /// State in between `self` and `next`, the state one simulation step later.
+    pub fn interpolate(&self, next: &Camera, alpha: f32) -> Camera {
+        return Camera {
+            position: self.position.lerp(next.position, alpha),
+            zoom: self.zoom + (next.zoom - self.zoom) * alpha,
+            rotation: self.rotation + (next.rotation - self.rotation) * alpha,
+
+            trauma: self.trauma + (next.trauma - self.trauma) * alpha,
+            shake_time: self.shake_time + (next.shake_time - self.shake_time) * alpha,
+
+            ..next.clone()
         };
     }
 
+    /// Adds screen shake, e.g. 0.3 for a small hit and 1 for an explosion.
+    pub fn shake(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(target) = self.target {
+            self.position = self.follow(target, delta_time);
+        }
+
+        self.trauma = (self.trauma - self.shake_decay * delta_time).max(0.0);
+        self.shake_time += delta_time;
+    }
+
+    /// Critically damped spring towards `target`, reaching it without overshoot.
+    fn follow(&mut self, target: Vec2, delta_time: f32) -> Vec2 {
+        if self.smooth_time <= 0.0 {
+            self.velocity = Vec2::ZERO;
+
+            return target;
+        }
+
+        let omega = 2.0 / self.smooth_time;
+        let x = omega * delta_time;
+        let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+
+        let change = self.position - target;
+        let temp = (self.velocity + omega * change) * delta_time;
+
+        self.velocity = (self.velocity - omega * temp) * decay;
+
+        return target + (change + temp) * decay;
+    }
+
+    /// Smooth pseudo random value in `[-1, 1]`, one independent curve per seed.
+    fn noise(&self, seed: f32) -> f32 {
+        let t = self.shake_time * 25.0 + seed * 17.0;
+
+        return (t.sin() + (t * 1.7 + 1.3).sin() * 0.5 + (t * 3.1 + 4.2).sin() * 0.25) / 1.75;
+    }
+
+    /// Position at the center of a view of `width` x `height` pixels, kept inside the bounds.
+    pub fn center(&self, (width, height): (u32, u32)) -> Vec2 {
+        let mut center = self.position;
+
+        if let Some((min, max)) = self.bounds {
+            // Half extents of the rotated view in world units
+            let (sin, cos) = self.rotation.sin_cos();
+            let half_width = width as f32 / 2.0 / self.zoom;
+            let half_height = height as f32 / 2.0 / self.zoom;
+            let extents = Vec2::new(
+                cos.abs() * half_width + sin.abs() * half_height,
+                sin.abs() * half_width + cos.abs() * half_height,
+            );
+
+            for axis in 0..2 {
+                let low = min[axis] + extents[axis];
+                let high = max[axis] - extents[axis];
+
+                // Views larger than the bounds are centered on them
+                center[axis] = if low > high { (min[axis] + max[axis]) / 2.0 } else { center[axis].clamp(low, high) };
+            }
+        }
+
+        return center;
+    }
+
+    /// Maps the world to a view of `width` x `height` pixels, centered on the camera position.
+    /// The inverse is what the shaders use to find the world position of a pixel.
     pub fn mvp (&self, (width, height): (u32, u32)) -> Mat4 {
-        let projection = Mat4::orthographic_rh(0f32, width as f32, 0f32, height as f32, -1f32, 1f32);
-        let view = Mat4::from_translation(Vec3::new(-self.position.x, -self.position.y, 0f32));
+        let half_width = width as f32 / 2.0;
+        let half_height = height as f32 / 2.0;
+
+        let shake = self.trauma * self.trauma;
+        let offset = Vec2::new(self.noise(0.0), self.noise(1.0)) * self.shake_offset * shake;
+        let angle = self.noise(2.0) * self.shake_angle * shake;
+
+        let center = self.center((width, height)) + offset;
+
+        let projection = Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, -1f32, 1f32);
+        let view = Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1f32))
+            * Mat4::from_rotation_z(-(self.rotation + angle))
+            * Mat4::from_translation(Vec3::new(-center.x, -center.y, 0f32));
 
         return projection * view;
     }
-}
\ No newline at end of file
+
+    /// Converts a position in pixels of a `width` x `height` view, y pointing down, to world space
+    /// with the inverse of `mvp`.
+    pub fn view_to_world(&self, position: Vec2, (width, height): (u32, u32)) -> Vec2 {
+        let x = 2.0 * position.x / width as f32 - 1.0;
+        let y = 1.0 - 2.0 * position.y / height as f32;
+
+        let world = self.mvp((width, height)).inverse() * Vec4::new(x, y, 0.0, 1.0);
+
+        return Vec2::new(world.x, world.y);
+    }
+}
@@ -0,0 +1,59 @@
+use glam::Vec2;
+
+/// Shape drawn by the debug overlay, in world space.
+pub enum DebugShape {
+    Line { a: Vec2, b: Vec2 },
+    /// Outline of an axis aligned rectangle.
+    Rect { min: Vec2, max: Vec2 },
+    /// Outline of a circle.
+    Circle { center: Vec2, radius: f32 },
+    /// Text starting at `position`, its size on screen doesn't change with the zoom.
+    Text { position: Vec2, text: String },
+}
+
+/// Shapes collected during a frame and drawn over it, to look into the state of the game. Nothing
+/// is collected while it is disabled, so the drawing calls can stay in place.
+pub struct DebugDraw {
+    pub enabled: bool,
+
+    /// Shapes of the frame and their color, in the order they were drawn.
+    pub shapes: Vec<(DebugShape, [u8; 4])>,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        return Self {
+            enabled: false,
+
+            shapes: Vec::new(),
+        };
+    }
+
+    /// Forgets the shapes of the last frame.
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    fn push(&mut self, shape: DebugShape, color: [u8; 4]) {
+        if self.enabled {
+            self.shapes.push((shape, color));
+        }
+    }
+
+    pub fn line(&mut self, a: Vec2, b: Vec2, color: [u8; 4]) {
+        self.push(DebugShape::Line { a, b }, color);
+    }
+
+    pub fn rect(&mut self, min: Vec2, max: Vec2, color: [u8; 4]) {
+        self.push(DebugShape::Rect { min, max }, color);
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: [u8; 4]) {
+        self.push(DebugShape::Circle { center, radius }, color);
+    }
+
+    /// Lines are separated by `\n`.
+    pub fn text(&mut self, position: Vec2, text: impl Into<String>, color: [u8; 4]) {
+        self.push(DebugShape::Text { position, text: text.into() }, color);
+    }
+}
@@ -0,0 +1,122 @@
+use glam::Vec2;
+
+use crate::font;
+use crate::logic::debug::DebugDraw;
+use crate::logic::play::{
+    light::Falloff,
+    Brush,
+    Play,
+};
+use crate::profiler::{
+    History,
+    Profiler,
+};
+use crate::ui::Ui;
+
+/// In-game interface: a frame rate counter, an editor panel toggled by the `toggle_editor` action
+/// to paint the level and tune the cursor light, and the frame time graphs toggled by the
+/// `toggle_profiler` action.
+pub struct Hud {
+    pub editor: bool,
+    pub profiler: bool,
+}
+
+impl Hud {
+    const MARGIN: f32 = 8.0;
+    const EDITOR_WIDTH: f32 = 240.0;
+    const PROFILER_WIDTH: f32 = 340.0;
+
+    const BRUSHES: [(&'static str, Brush); 4] = [
+        ("Light", Brush::Light),
+        ("Wall", Brush::Tile(1)),
+        ("Glowing wall", Brush::Tile(2)),
+        ("Erase", Brush::Tile(0)),
+    ];
+
+    pub fn new() -> Self {
+        return Self {
+            editor: false,
+            profiler: false,
+        };
+    }
+
+    /// Declares the widgets of the frame.
+    pub fn build(&mut self, ui: &mut Ui, play: &mut Play, debug: &mut DebugDraw, profiler: &Profiler) {
+        // Averaged over the history, so the counter stays readable
+        let frame_time = profiler.frame.recent_average();
+
+        let counter = format!("{:.0} fps {:.2} ms", 1000.0 / frame_time.max(1e-3), frame_time);
+        let position = Vec2::splat(Self::MARGIN);
+        let counter_size = font::measure(&counter, Ui::TEXT_SCALE);
+
+        ui.rect(position - 4.0, position + counter_size + 4.0, [0, 0, 0, 160]);
+        ui.text(position, &counter, [255; 4]);
+
+        if self.profiler {
+            let position = position + Vec2::new(0.0, counter_size.y + Self::MARGIN);
+
+            ui.panel("Profiler", position, Self::PROFILER_WIDTH, |ui| {
+                Self::graph(ui, "frame", &profiler.frame);
+                Self::graph(ui, "cpu", &profiler.cpu);
+
+                if profiler.gpu.is_empty() {
+                    ui.label("No GPU timestamps");
+                }
+
+                for (section, history) in &profiler.gpu {
+                    Self::graph(ui, section, history);
+                }
+            });
+        }
+
+        if !self.editor {
+            return;
+        }
+
+        let position = Vec2::new(ui.window_size().x - Self::EDITOR_WIDTH - Self::MARGIN, Self::MARGIN);
+
+        ui.panel("Editor", position, Self::EDITOR_WIDTH, |ui| {
+            ui.label("Brush");
+
+            for (label, brush) in Self::BRUSHES {
+                if ui.selectable(label, play.brush == brush) {
+                    play.brush = brush;
+                }
+            }
+
+            ui.label("Cursor light");
+
+            let light = &mut play.cursor_light;
+            let range = match &mut light.falloff {
+                Falloff::Linear { radius } | Falloff::Smooth { radius } => radius,
+                Falloff::InverseSquare { cutoff, .. } => cutoff,
+            };
+
+            ui.slider("Range", range, 20.0..=1000.0);
+            ui.slider("Intensity", &mut light.intensity, 0.0..=300.0);
+            ui.slider("Red", &mut light.color.x, 0.0..=1.0);
+            ui.slider("Green", &mut light.color.y, 0.0..=1.0);
+            ui.slider("Blue", &mut light.color.z, 0.0..=1.0);
+
+            ui.label("Level");
+            ui.slider("Hour", &mut play.time_of_day, 0.0..=24.0);
+
+            if ui.selectable("Fog of war", play.fog_of_war.enabled) {
+                play.fog_of_war.enabled = !play.fog_of_war.enabled;
+            }
+
+            if ui.selectable("Debug overlay", debug.enabled) {
+                debug.enabled = !debug.enabled;
+            }
+        });
+    }
+
+    /// Graph of a history scaled to its recent maximum, labelled with its recent average and
+    /// maximum.
+    fn graph(ui: &mut Ui, name: &str, history: &History) {
+        let max = history.recent_max();
+        let label = format!("{} {:.2} / {:.2} ms", name, history.recent_average(), max);
+
+        ui.graph(&label, history.samples(), History::LENGTH, max.max(1e-3));
+    }
+}
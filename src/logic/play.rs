@@ -1,29 +1,477 @@
-use glam::Vec2;
+use glam::{
+    Vec2,
+    Vec3,
+};
 
+use crate::input::Input;
 use crate::logic::camera::Camera;
+use crate::logic::debug::DebugDraw;
+use crate::logic::play::entity::{
+    Entities,
+    Entity,
+    Layer,
+    Prop,
+    Renderable,
+    Sprite,
+    Transform,
+};
+use crate::logic::play::fog::FogOfWar;
+use crate::logic::play::light::{
+    Falloff,
+    PointLight,
+};
+use crate::logic::play::npc::{
+    Npc,
+    Surroundings,
+};
+use crate::logic::play::occluder::{
+    Occluder,
+    Shape,
+};
+use crate::logic::play::particle::Emitter;
+use crate::logic::play::pathfinding::{
+    DiagonalMovement,
+    Pathfinder,
+};
+use crate::logic::play::player::{
+    Movement,
+    Player,
+};
+use crate::logic::play::visibility::VisibilityPolygon;
 use crate::logic::play::world::World;
 
+pub mod entity;
+pub mod fog;
+pub mod light;
+pub mod lighting;
+pub mod npc;
+pub mod occluder;
+pub mod particle;
+pub mod pathfinding;
+pub mod player;
+pub mod sdf;
+pub mod sky;
+pub mod systems;
+pub mod visibility;
 pub mod world;
 
+/// What the `place` action puts under the cursor.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Brush {
+    /// A copy of the cursor light.
+    Light,
+    /// A tile of the given value, see `World::tiles`.
+    Tile(u32),
+}
+
 pub struct Play {
     pub camera: Camera,
+    /// Camera as it was before the last simulation step.
+    previous_camera: Camera,
+    /// Position of the rendered frame between the previous and the last step, in `[0, 1)`.
+    pub interpolation: f32,
 
     pub world: World,
+    /// Lights, occluders, characters and particles of the level.
+    pub entities: Entities,
+    /// Entity controlled by the input, followed by the camera.
+    pub player: Entity,
+    pub fog_of_war: FogOfWar,
+    /// Paths over the tiles, cached until they change.
+    pub pathfinder: Pathfinder,
+    /// Cursor position in window pixels.
     pub mouse_position: Vec2,
+    /// Cursor position in world space, as seen in the last rendered frame.
+    pub cursor_world_position: Vec2,
+
+    /// Light attached to the cursor, its position is replaced by the cursor position in world
+    /// space every frame.
+    pub cursor_light: PointLight,
+    pub brush: Brush,
+
+    /// Time of day in hours, drives the sun of outdoor levels.
+    pub time_of_day: f32,
+    /// Simulated seconds since the level started.
+    pub elapsed: f32,
 }
 
 impl Play {
+    /// Camera rotation speed in radians per second.
+    const ROTATION_SPEED: f32 = 1.5;
+    /// Hours of the day passing per simulated second, a full day takes 8 minutes.
+    const HOURS_PER_SECOND: f32 = 0.05;
+
+    const PLAYER_COLOR: [u8; 4] = [230, 230, 255, 255];
+    const NPC_HALF_SIZE: f32 = 7.0;
+
+    /// Lights in the scene at most, the size of the light buffer. Past it the particle lights,
+    /// then the last placed lights, are left out.
+    pub const MAX_LIGHTS: usize = 64;
+    /// Particle lights kept at most, so they leave room for the other lights in the light buffer.
+    const MAX_PARTICLE_LIGHTS: usize = 16;
+
+    /// Rays cast around the cursor by `draw_debug`, and how far.
+    const DEBUG_RAYS: usize = 16;
+    const DEBUG_RAY_LENGTH: f32 = 200.0;
+
     pub fn new() -> Self {
+        let world = World::outdoor();
+        let mut entities = Entities::new();
+
+        let player_position = Vec2::new(510.0, 70.0);
+        let player = Self::spawn_player(&mut entities, player_position);
+
+        Self::spawn_light(&mut entities, PointLight::new(Vec2::new(300.0, 300.0), Vec3::new(1.0, 0.6, 0.2), 80.0, Falloff::InverseSquare { radius: 150.0, cutoff: 600.0 }));
+        Self::spawn_light(&mut entities, PointLight::new(Vec2::new(700.0, 300.0), Vec3::new(0.3, 0.5, 1.0), 80.0, Falloff::Smooth { radius: 500.0 }));
+
+        // A sliding door, a rolling boulder and a spinning crate
+        Self::spawn_prop(&mut entities, Prop::SlidingDoor { min: Vec2::new(240.0, 220.0), max: Vec2::new(256.0, 280.0), travel: 50.0 }, Vec3::new(0.35, 0.2, 0.1));
+        Self::spawn_prop(&mut entities, Prop::RollingBoulder { center: Vec2::new(650.0, 160.0), radius: 14.0, travel: 60.0 }, Vec3::new(0.3, 0.3, 0.3));
+        Self::spawn_prop(&mut entities, Prop::SpinningCrate { center: Vec2::new(350.0, 450.0), half_size: 12.0 }, Vec3::new(0.5, 0.35, 0.15));
+
+        Self::spawn_npc(&mut entities, vec![Vec2::new(300.0, 300.0), Vec2::new(500.0, 120.0), Vec2::new(700.0, 300.0), Vec2::new(500.0, 480.0)]);
+        Self::spawn_npc(&mut entities, vec![Vec2::new(250.0, 150.0), Vec2::new(250.0, 450.0)]);
+
+        Self::spawn_emitter(&mut entities, Vec2::new(430.0, 100.0), Emitter::sparks(Vec2::new(0.4, 1.0)));
+        Self::spawn_emitter(&mut entities, Vec2::new(300.0, 280.0), Emitter::dust());
+        Self::spawn_emitter(&mut entities, Vec2::new(700.0, 150.0), Emitter::smoke());
+
+        let occluders: Vec<Occluder> = entities.occluders.iter().map(|(_, occluder)| occluder.clone()).collect();
+        let fog_of_war = FogOfWar::new(&world, &occluders, player_position);
+
+        let mut camera = Camera::new();
+        camera.position = player_position;
+        camera.bounds = Some((Vec2::ZERO, World::size()));
+
         return Self {
-            camera: Camera::new(),
+            previous_camera: camera.clone(),
+            interpolation: 0.0,
+            camera,
 
-            world: World::new(),
+            world,
+            entities,
+            player,
+            fog_of_war,
+            pathfinder: Pathfinder::new(DiagonalMovement::NoCornerCutting),
             mouse_position: Vec2::ZERO,
+            cursor_world_position: Vec2::ZERO,
+
+            cursor_light: PointLight::new(Vec2::ZERO, Vec3::new(1.0, 1.0, 0.0), 100.0, Falloff::Linear { radius: 700.0 }),
+            brush: Brush::Light,
+
+            time_of_day: 12.0,
+            elapsed: 0.0,
         };
     }
 
-    pub fn process_mouse_position(&mut self, position: (u32, u32)) {
-        self.mouse_position = Vec2::new(position.0 as f32, position.1 as f32);
+    pub fn spawn_player(entities: &mut Entities, position: Vec2) -> Entity {
+        let player = Player::new();
+
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, Transform::new(position));
+        entities.renderables.insert(entity, Renderable {
+            sprite: Some(Sprite::Player),
+            // Kept readable in the dark, and over the agents
+            layer: Layer::Unlit,
+            depth: 1.0,
+            ..Renderable::new(player.half_size, Self::PLAYER_COLOR)
+        });
+        entities.players.insert(entity, player);
+
+        return entity;
+    }
+
+    pub fn spawn_light(entities: &mut Entities, light: PointLight) -> Entity {
+        let entity = entities.spawn();
+        entities.lights.insert(entity, light);
+
+        return entity;
+    }
+
+    /// Occluder moved by `prop`, placed where it is when the level starts.
+    pub fn spawn_prop(entities: &mut Entities, prop: Prop, color: Vec3) -> Entity {
+        let occluder = Occluder::new(prop.shape(0.0), color);
+
+        let entity = entities.spawn();
+        entities.previous_occluders.insert(entity, occluder.clone());
+        entities.occluders.insert(entity, occluder);
+        entities.props.insert(entity, prop);
+
+        return entity;
+    }
+
+    /// Agent standing on the first point of its patrol route.
+    pub fn spawn_npc(entities: &mut Entities, route: Vec<Vec2>) -> Entity {
+        let position = route.first().copied().unwrap_or(Vec2::ZERO);
+
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, Transform::new(position));
+        entities.renderables.insert(entity, Renderable {
+            sprite: Some(Sprite::Guard),
+            ..Renderable::new(Vec2::splat(Self::NPC_HALF_SIZE), [255; 4])
+        });
+        entities.npcs.insert(entity, Npc::new(route));
+
+        return entity;
+    }
+
+    pub fn spawn_emitter(entities: &mut Entities, position: Vec2, emitter: Emitter) -> Entity {
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, Transform::new(position));
+        entities.emitters.insert(entity, emitter);
+
+        return entity;
+    }
+
+    /// Position of the player at the last simulation step.
+    pub fn player_position(&self) -> Vec2 {
+        return self.entities.transforms.get(self.player).map_or(Vec2::ZERO, |transform| transform.position);
+    }
+
+    /// Simulation step of `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f32, input: &Input) {
+        self.previous_camera = self.camera.clone();
+        systems::begin_step(&mut self.entities);
+
+        self.elapsed += delta_time;
+        self.advance_time_of_day(delta_time * Self::HOURS_PER_SECOND);
+        systems::animate_props(&mut self.entities, self.elapsed);
+
+        if input.action_pressed("toggle_movement") {
+            for (_, player) in self.entities.players.iter_mut() {
+                player.movement = match player.movement {
+                    Movement::Platformer => Movement::TopDown,
+                    Movement::TopDown => Movement::Platformer,
+                };
+            }
+        }
+
+        if input.action_pressed("toggle_fog_of_war") {
+            self.fog_of_war.enabled = !self.fog_of_war.enabled;
+        }
+
+        systems::move_players(&mut self.entities, delta_time, &self.world, input);
+
+        let player_position = self.player_position();
+        let occluders = self.occluders();
+        let lights = self.scene_lights(self.cursor_world_position);
+
+        self.fog_of_war.update(&mut self.world, &occluders, player_position);
+
+        let surroundings = Surroundings {
+            world: &self.world,
+            occluders: &occluders,
+            lights: &lights,
+            time_of_day: self.time_of_day,
+        };
+
+        systems::think_npcs(&mut self.entities, delta_time, &surroundings, &mut self.pathfinder, player_position);
+        systems::emit_particles(&mut self.entities, delta_time);
+        systems::update_particles(&mut self.entities, delta_time, &self.world);
+
+        self.camera.rotation += input.action_axis("rotate_right", "rotate_left") * Self::ROTATION_SPEED * delta_time;
+
+        if input.action_pressed("zoom_in") {
+            self.camera.zoom = (self.camera.zoom * 1.25).min(8.0);
+        }
+
+        if input.action_pressed("zoom_out") {
+            self.camera.zoom = (self.camera.zoom / 1.25).max(0.25);
+        }
+
+        if input.action_pressed("shake") {
+            self.camera.shake(0.5);
+        }
+
+        if input.action_pressed("place") {
+            match self.brush {
+                Brush::Light => {
+                    Self::spawn_light(&mut self.entities, PointLight {
+                        position: self.cursor_world_position,
+                        ..self.cursor_light
+                    });
+                }
+                Brush::Tile(value) => self.world.set_tile(World::tile_at(self.cursor_world_position), value),
+            }
+        }
+
+        if input.action_pressed("remove") {
+            match self.brush {
+                Brush::Light => self.remove_light(self.cursor_world_position),
+                Brush::Tile(_) => self.world.set_tile(World::tile_at(self.cursor_world_position), 0),
+            }
+        }
+
+        self.camera.target = Some(player_position);
+        self.camera.update(delta_time);
+    }
+
+    /// Lights of the level preceded by the cursor light at `cursor_position` and followed by the
+    /// particle lights, the first `MAX_LIGHTS` of them, like in the light buffer.
+    pub fn scene_lights(&self, cursor_position: Vec2) -> Vec<PointLight> {
+        let cursor_light = PointLight {
+            position: cursor_position,
+            ..self.cursor_light
+        };
+
+        return std::iter::once(cursor_light)
+            .chain(self.entities.lights.iter().map(|(_, light)| *light))
+            .chain(self.particle_lights())
+            .take(Self::MAX_LIGHTS)
+            .collect();
+    }
+
+    /// Lights of the glowing particles, the brightest `MAX_PARTICLE_LIGHTS` of them.
+    pub fn particle_lights(&self) -> Vec<PointLight> {
+        let mut lights: Vec<PointLight> = self.entities.particles.iter()
+            .filter_map(|(entity, particle)| particle.light(self.entities.transforms.get(entity)?.position))
+            .collect();
+
+        lights.sort_by(|a, b| b.intensity.total_cmp(&a.intensity));
+        lights.truncate(Self::MAX_PARTICLE_LIGHTS);
+
+        return lights;
+    }
+
+    /// Occluders at the last simulation step.
+    pub fn occluders(&self) -> Vec<Occluder> {
+        return self.entities.occluders.iter().map(|(_, occluder)| occluder.clone()).collect();
+    }
+
+    /// Camera to render with, interpolated between the last two steps.
+    pub fn view_camera(&self) -> Camera {
+        return self.previous_camera.interpolate(&self.camera, self.interpolation);
+    }
+
+    /// Occluders to render with, interpolated between the last two steps.
+    pub fn view_occluders(&self) -> Vec<Occluder> {
+        return self.entities.occluders.iter()
+            .map(|(entity, occluder)| match self.entities.previous_occluders.get(entity) {
+                Some(previous) => Occluder {
+                    shape: previous.shape.interpolate(&occluder.shape, self.interpolation),
+                    ..occluder.clone()
+                },
+                None => occluder.clone(),
+            })
+            .collect();
+    }
+
+    /// What can be seen from `origin` up to `radius`, through the tiles, the field and the occluders.
+    pub fn visibility(&self, origin: Vec2, radius: f32) -> VisibilityPolygon {
+        return VisibilityPolygon::new(&self.world, &self.occluders(), origin, radius);
+    }
+
+    /// Removes the light closest to `position`, if any is within a tile of it.
+    fn remove_light(&mut self, position: Vec2) {
+        let closest = self.entities.lights.iter()
+            .map(|(entity, light)| (entity, light.position.distance(position)))
+            .filter(|(_, distance)| *distance < World::TILE_SIZE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((entity, _)) = closest {
+            self.entities.despawn(entity);
+        }
+    }
+
+    /// Draws the tile grid, the colliders, the reach of the lights, rays cast from the cursor and
+    /// the paths of the agents, when the debug drawing is enabled.
+    pub fn draw_debug(&self, debug: &mut DebugDraw) {
+        if !debug.enabled {
+            return;
+        }
+
+        let size = World::size();
+
+        for x in 0..=World::WIDTH {
+            let x = x as f32 * World::TILE_SIZE;
+
+            debug.line(Vec2::new(x, 0.0), Vec2::new(x, size.y), [255, 255, 255, 40]);
+        }
+
+        for y in 0..=World::HEIGHT {
+            let y = y as f32 * World::TILE_SIZE;
+
+            debug.line(Vec2::new(0.0, y), Vec2::new(size.x, y), [255, 255, 255, 40]);
+        }
+
+        for (entity, player) in self.entities.players.iter() {
+            if let Some(transform) = self.entities.transforms.get(entity) {
+                let position = transform.interpolated_position(self.interpolation);
+
+                debug.rect(position - player.half_size, position + player.half_size, [0, 255, 0, 255]);
+            }
+        }
+
+        for occluder in self.view_occluders() {
+            let color = [0, 255, 255, 255];
+
+            match occluder.shape {
+                Shape::Circle { center, radius } => debug.circle(center, radius, color),
+                Shape::Aabb { min, max } => debug.rect(min, max, color),
+                Shape::Polygon(vertices) => {
+                    for (index, vertex) in vertices.iter().enumerate() {
+                        debug.line(*vertex, vertices[(index + 1) % vertices.len()], color);
+                    }
+                }
+            }
+        }
+
+        // In the color of the light, at full brightness
+        for light in self.scene_lights(self.cursor_world_position) {
+            let color = (light.color / light.color.max_element().max(1e-3) * 255.0).as_uvec3();
+            let color = [color.x as u8, color.y as u8, color.z as u8, 255];
+
+            debug.circle(light.position, 3.0, color);
+            debug.circle(light.position, light.falloff.range(), color);
+        }
+
+        let origin = self.cursor_world_position;
+        let occluders = self.occluders();
+
+        for index in 0..Self::DEBUG_RAYS {
+            let direction = Vec2::from_angle(index as f32 / Self::DEBUG_RAYS as f32 * std::f32::consts::TAU);
+            let distance = VisibilityPolygon::cast(&self.world, &occluders, origin, direction, Self::DEBUG_RAY_LENGTH);
+            let end = origin + direction * distance;
+
+            debug.line(origin, end, [255, 160, 0, 255]);
+
+            if distance < Self::DEBUG_RAY_LENGTH {
+                debug.rect(end - 2.0, end + 2.0, [255, 160, 0, 255]);
+            }
+        }
+
+        let tile = World::tile_at(origin);
+        debug.text(origin + Vec2::new(6.0, -6.0), format!("{}, {}", tile.x, tile.y), [255; 4]);
+
+        for (entity, npc) in self.entities.npcs.iter() {
+            let Some(transform) = self.entities.transforms.get(entity) else {
+                continue;
+            };
+
+            let position = transform.interpolated_position(self.interpolation);
+
+            for (index, point) in npc.route.iter().enumerate() {
+                debug.line(*point, npc.route[(index + 1) % npc.route.len()], [255, 0, 255, 80]);
+            }
+
+            let mut previous = position;
+
+            for point in npc.path() {
+                debug.line(previous, *point, [255, 0, 255, 255]);
+                previous = *point;
+            }
+
+            debug.text(position + Vec2::new(-Self::NPC_HALF_SIZE, -Self::NPC_HALF_SIZE - 4.0), format!("{:?}\n{:.2}", npc.state, npc.light_level), [255; 4]);
+        }
+    }
+
+    pub fn advance_time_of_day(&mut self, hours: f32) {
+        self.time_of_day = (self.time_of_day + hours).rem_euclid(24.0);
+    }
+
+    pub fn process_cursor(&mut self, window_position: Vec2, world_position: Vec2) {
+        self.mouse_position = window_position;
+        self.cursor_world_position = world_position;
     }
 }
 
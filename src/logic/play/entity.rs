@@ -0,0 +1,278 @@
+use glam::Vec2;
+
+use crate::logic::play::{
+    light::PointLight,
+    npc::Npc,
+    occluder::{
+        Occluder,
+        Shape,
+    },
+    particle::{
+        Emitter,
+        Particle,
+    },
+    player::Player,
+};
+
+/// Handle of a game object. The generation tells apart the objects reusing the same index, so a
+/// handle kept after a despawn never reaches the next object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+/// Components of one type, indexed by entity.
+pub struct Storage<T> {
+    entries: Vec<Option<(u32, T)>>,
+}
+
+impl<T> Storage<T> {
+    pub fn new() -> Self {
+        return Self {
+            entries: Vec::new(),
+        };
+    }
+
+    /// Attaches the component to the entity, replacing the one it had.
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        let index = entity.index as usize;
+
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || None);
+        }
+
+        self.entries[index] = Some((entity.generation, component));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let entry = self.entries.get_mut(entity.index as usize)?;
+
+        if entry.as_ref().is_some_and(|(generation, _)| *generation == entity.generation) {
+            return entry.take().map(|(_, component)| component);
+        }
+
+        return None;
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        return match self.entries.get(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        };
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        return match self.entries.get_mut(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        };
+    }
+
+    /// Components with their entity, in the order of the entity indices.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        return self.entries.iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_ref().map(|(generation, component)| (Entity { index: index as u32, generation: *generation }, component)));
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        return self.entries.iter_mut()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_mut().map(|(generation, component)| (Entity { index: index as u32, generation: *generation }, component)));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.iter().filter(|entry| entry.is_some()).count();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.iter().all(|entry| entry.is_none());
+    }
+}
+
+/// Where an entity is, at the last two simulation steps.
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub position: Vec2,
+    /// Position before the last simulation step, for interpolation.
+    pub previous_position: Vec2,
+}
+
+impl Transform {
+    pub fn new(position: Vec2) -> Self {
+        return Self {
+            position,
+            previous_position: position,
+        };
+    }
+
+    /// Position to render with, `alpha` in between the last two steps.
+    pub fn interpolated_position(&self, alpha: f32) -> Vec2 {
+        return self.previous_position.lerp(self.position, alpha);
+    }
+}
+
+/// Image of a renderable, the renderer holds the pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Sprite {
+    Player,
+    Guard,
+}
+
+/// When a renderable is drawn relative to the lighting.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layer {
+    /// Before the lighting, lit and shadowed like the floor under it.
+    Lit,
+    /// Over the lighting, with its own colors.
+    Unlit,
+}
+
+/// Rectangle drawn at the transform of the entity, showing a sprite or a flat color.
+#[derive(Clone, Copy)]
+pub struct Renderable {
+    pub half_size: Vec2,
+    /// Linear RGBA, multiplies the sprite.
+    pub color: [u8; 4],
+    pub sprite: Option<Sprite>,
+
+    pub layer: Layer,
+    /// Order within the layer, renderables with a higher depth are drawn over the others.
+    pub depth: f32,
+}
+
+impl Renderable {
+    pub fn new(half_size: Vec2, color: [u8; 4]) -> Self {
+        return Self {
+            half_size,
+            color,
+            sprite: None,
+
+            layer: Layer::Lit,
+            depth: 0.0,
+        };
+    }
+}
+
+/// Scripted movement of an occluder, driven by the time since the level started.
+#[derive(Clone, Copy)]
+pub enum Prop {
+    /// Box from `min` to `max`, sliding up and back down by `travel`.
+    SlidingDoor { min: Vec2, max: Vec2, travel: f32 },
+    /// Circle rolling back and forth horizontally around `center`.
+    RollingBoulder { center: Vec2, radius: f32, travel: f32 },
+    /// Square spinning around `center`.
+    SpinningCrate { center: Vec2, half_size: f32 },
+}
+
+impl Prop {
+    /// Outline of the prop `elapsed` seconds after the level started.
+    pub fn shape(&self, elapsed: f32) -> Shape {
+        return match *self {
+            Prop::SlidingDoor { min, max, travel } => {
+                let opening = Vec2::new(0.0, travel * (0.5 + 0.5 * (elapsed * 0.7).sin()));
+
+                Shape::Aabb { min: min + opening, max: max + opening }
+            }
+            Prop::RollingBoulder { center, radius, travel } => Shape::Circle {
+                center: center + Vec2::new(travel * (elapsed * 0.8).sin(), 0.0),
+                radius,
+            },
+            Prop::SpinningCrate { center, half_size } => {
+                let rotation = Vec2::from_angle(elapsed);
+
+                Shape::Polygon(
+                    [Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0)]
+                        .iter()
+                        .map(|corner| center + rotation.rotate(*corner * half_size))
+                        .collect(),
+                )
+            }
+        };
+    }
+}
+
+/// Game objects of a level, as components stored by type. Systems run over the storages they
+/// need, see `systems`.
+pub struct Entities {
+    /// Current generation of every index, odd while the index is in use.
+    generations: Vec<u32>,
+    free: Vec<u32>,
+
+    pub transforms: Storage<Transform>,
+    pub renderables: Storage<Renderable>,
+
+    pub lights: Storage<PointLight>,
+    pub occluders: Storage<Occluder>,
+    /// Occluders as they were before the last simulation step.
+    pub previous_occluders: Storage<Occluder>,
+    pub props: Storage<Prop>,
+
+    pub players: Storage<Player>,
+    pub npcs: Storage<Npc>,
+    pub particles: Storage<Particle>,
+    pub emitters: Storage<Emitter>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        return Self {
+            generations: Vec::new(),
+            free: Vec::new(),
+
+            transforms: Storage::new(),
+            renderables: Storage::new(),
+
+            lights: Storage::new(),
+            occluders: Storage::new(),
+            previous_occluders: Storage::new(),
+            props: Storage::new(),
+
+            players: Storage::new(),
+            npcs: Storage::new(),
+            particles: Storage::new(),
+            emitters: Storage::new(),
+        };
+    }
+
+    /// New entity without any component.
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            self.generations[index as usize] += 1;
+
+            return Entity { index, generation: self.generations[index as usize] };
+        }
+
+        self.generations.push(1);
+
+        return Entity { index: self.generations.len() as u32 - 1, generation: 1 };
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        return self.generations.get(entity.index as usize) == Some(&entity.generation) && entity.generation % 2 == 1;
+    }
+
+    /// Removes the entity and all of its components.
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+
+        self.transforms.remove(entity);
+        self.renderables.remove(entity);
+
+        self.lights.remove(entity);
+        self.occluders.remove(entity);
+        self.previous_occluders.remove(entity);
+        self.props.remove(entity);
+
+        self.players.remove(entity);
+        self.npcs.remove(entity);
+        self.particles.remove(entity);
+        self.emitters.remove(entity);
+
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+    }
+}
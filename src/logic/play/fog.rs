@@ -0,0 +1,91 @@
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use crate::logic::play::{
+    occluder::Occluder,
+    visibility::VisibilityPolygon,
+    world::World,
+};
+
+/// Hides the level outside of the view of the player. Tiles in view are marked as explored on the
+/// world as they are seen, the renderer shows them dimmed once they leave the view.
+pub struct FogOfWar {
+    pub enabled: bool,
+    /// How far the player sees, in world units.
+    pub radius: f32,
+
+    /// View of the player at the last simulation step.
+    pub view: VisibilityPolygon,
+    /// Column major like the tiles, whether each tile is in view.
+    visible: [bool; World::HEIGHT * World::WIDTH],
+}
+
+impl FogOfWar {
+    const RADIUS: f32 = 320.0;
+
+    pub fn new(world: &World, occluders: &[Occluder], origin: Vec2) -> Self {
+        return Self {
+            enabled: false,
+            radius: Self::RADIUS,
+
+            view: VisibilityPolygon::new(world, occluders, origin, Self::RADIUS),
+            visible: [false; World::HEIGHT * World::WIDTH],
+        };
+    }
+
+    /// Recomputes the view from `origin` and explores the tiles it reaches. Nothing is explored
+    /// while the fog is disabled.
+    pub fn update(&mut self, world: &mut World, occluders: &[Occluder], origin: Vec2) {
+        if !self.enabled {
+            return;
+        }
+
+        self.view = VisibilityPolygon::new(world, occluders, origin, self.radius);
+        self.visible = [false; World::HEIGHT * World::WIDTH];
+
+        // Empty tiles whose center is in view
+        let min = ((origin - self.radius) / World::TILE_SIZE).floor().as_ivec2().max(IVec2::ZERO);
+        let max = ((origin + self.radius) / World::TILE_SIZE).floor().as_ivec2().min(IVec2::new(World::WIDTH as i32 - 1, World::HEIGHT as i32 - 1));
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let tile = IVec2::new(x, y);
+                let center = (tile.as_vec2() + 0.5) * World::TILE_SIZE;
+
+                if !world.is_solid(tile) && self.view.contains(center) {
+                    Self::set_visible(&mut self.visible, tile);
+                }
+            }
+        }
+
+        // Tiles on both sides of the outline, the walls it stops on and the empty tiles before them
+        for vertex in &self.view.vertices {
+            let direction = (*vertex - origin).normalize_or_zero();
+
+            Self::set_visible(&mut self.visible, ((*vertex - direction * 0.5) / World::TILE_SIZE).floor().as_ivec2());
+
+            if vertex.distance(origin) < self.radius - 0.5 {
+                Self::set_visible(&mut self.visible, ((*vertex + direction * 0.5) / World::TILE_SIZE).floor().as_ivec2());
+            }
+        }
+
+        for (index, visible) in self.visible.iter().enumerate() {
+            if *visible {
+                world.explore(IVec2::new((index / World::HEIGHT) as i32, (index % World::HEIGHT) as i32));
+            }
+        }
+    }
+
+    fn set_visible(visible: &mut [bool; World::HEIGHT * World::WIDTH], tile: IVec2) {
+        if let Some(index) = World::index(tile) {
+            visible[index] = true;
+        }
+    }
+
+    /// Whether the tile was in view at the last step, always false while the fog is disabled.
+    pub fn is_visible(&self, tile: IVec2) -> bool {
+        return self.enabled && World::index(tile).is_some_and(|index| self.visible[index]);
+    }
+}
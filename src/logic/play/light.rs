@@ -0,0 +1,92 @@
+use glam::{
+    Vec2,
+    Vec3,
+};
+
+/// How the intensity of a light decreases with the distance. Every model is clamped to be
+/// non-negative and mirrors `falloff` in `lighting.wgsl`.
+#[derive(Clone, Copy)]
+pub enum Falloff {
+    /// Decreases linearly and reaches zero at `radius`.
+    Linear { radius: f32 },
+    /// Physical `1 / d²` decrease, halved at `radius` and shifted to reach zero at `cutoff`.
+    InverseSquare { radius: f32, cutoff: f32 },
+    /// Smooth window `(1 - (d / radius)⁴)²`, flat near the light and reaching zero at `radius`.
+    Smooth { radius: f32 },
+}
+
+impl Falloff {
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        match *self {
+            Falloff::Linear { radius } => {
+                return (1.0 - distance / radius).max(0.0);
+            }
+            Falloff::InverseSquare { radius, cutoff } => {
+                let attenuation = 1.0 / (1.0 + (distance / radius).powi(2));
+                let attenuation_cutoff = 1.0 / (1.0 + (cutoff / radius).powi(2));
+
+                return ((attenuation - attenuation_cutoff) / (1.0 - attenuation_cutoff)).max(0.0);
+            }
+            Falloff::Smooth { radius } => {
+                let window = (1.0 - (distance / radius).powi(4)).clamp(0.0, 1.0);
+
+                return window * window;
+            }
+        }
+    }
+
+    /// Distance at which the attenuation reaches zero.
+    pub fn range(&self) -> f32 {
+        return match *self {
+            Falloff::Linear { radius } => radius,
+            Falloff::InverseSquare { cutoff, .. } => cutoff,
+            Falloff::Smooth { radius } => radius,
+        };
+    }
+
+    /// Identifier of the model on the GPU side.
+    pub fn kind(&self) -> u32 {
+        return match self {
+            Falloff::Linear { .. } => 0,
+            Falloff::InverseSquare { .. } => 1,
+            Falloff::Smooth { .. } => 2,
+        };
+    }
+
+    /// Parameters of the model on the GPU side.
+    pub fn parameters(&self) -> Vec2 {
+        return match *self {
+            Falloff::Linear { radius } => Vec2::new(radius, 0.0),
+            Falloff::InverseSquare { radius, cutoff } => Vec2::new(radius, cutoff),
+            Falloff::Smooth { radius } => Vec2::new(radius, 0.0),
+        };
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PointLight {
+    pub position: Vec2,
+
+    /// Linear RGB, not sRGB encoded. The intensity scales it and may push it above 1.
+    pub color: Vec3,
+    pub intensity: f32,
+
+    pub falloff: Falloff,
+}
+
+impl PointLight {
+    pub fn new(position: Vec2, color: Vec3, intensity: f32, falloff: Falloff) -> Self {
+        return Self {
+            position,
+
+            color,
+            intensity,
+
+            falloff,
+        };
+    }
+
+    pub fn radiance(&self) -> Vec3 {
+        return self.color * self.intensity;
+    }
+}
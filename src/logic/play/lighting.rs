@@ -0,0 +1,188 @@
+use glam::{
+    IVec2,
+    Vec2,
+    Vec3,
+};
+
+use crate::logic::play::{
+    light::PointLight,
+    occluder::{
+        self,
+        Occluder,
+    },
+    sky::Sky,
+    world::World,
+};
+
+// CPU reference of the lighting model implemented in `lighting.wgsl`. Every function mirrors
+// its WGSL counterpart step by step, so gameplay code can query the light the player sees.
+
+/// Distance kept before a tile, an occluder or the field when a ray stops on it, and past the edge
+/// of the world when it leaves.
+const SURFACE_OFFSET: f32 = 1.0;
+/// Tile edges a ray crosses at most before leaving the grid.
+const TILE_STEPS: usize = World::WIDTH + World::HEIGHT + 2;
+
+pub struct RayHit {
+    pub hit: bool,
+
+    /// Position just before the hit tile, field or occluder, or the position where the ray left the world.
+    pub position: Vec2,
+}
+
+/// Whether nothing blocks the light between the two points. Like `World::line_of_sight`, a point
+/// sees itself unless it lies in a tile or the field.
+pub fn ray_check(world: &World, occluders: &[Occluder], ray_origin: Vec2, ray_target: Vec2) -> bool {
+    let length = (ray_target - ray_origin).length();
+
+    if length == 0.0 {
+        return !world.is_solid((ray_origin / World::TILE_SIZE).floor().as_ivec2()) && !world.field.is_solid(ray_origin);
+    }
+
+    let ray_direction = (ray_target - ray_origin) / length;
+
+    if obstacle_distance(world, occluders, ray_origin, ray_direction) <= length {
+        return false;
+    }
+
+    return tile_distance(world, ray_origin, ray_direction, length) > length;
+}
+
+pub fn ray_hit(world: &World, occluders: &[Occluder], ray_origin: Vec2, ray_direction: Vec2) -> RayHit {
+    let obstacle_distance = obstacle_distance(world, occluders, ray_origin, ray_direction);
+    let tile_distance = tile_distance(world, ray_origin, ray_direction, obstacle_distance);
+
+    if tile_distance <= obstacle_distance {
+        // Tiles outside the grid let the ray leave the world, just past its edge
+        let beyond = ray_origin + ray_direction * (tile_distance + SURFACE_OFFSET);
+
+        if !World::contains(beyond) {
+            return RayHit {
+                hit: false,
+                position: beyond,
+            };
+        }
+
+        return RayHit {
+            hit: true,
+            position: ray_origin + ray_direction * (tile_distance - SURFACE_OFFSET).max(0.0),
+        };
+    }
+
+    return RayHit {
+        hit: true,
+        position: ray_origin + ray_direction * (obstacle_distance - SURFACE_OFFSET).max(0.0),
+    };
+}
+
+/// Distance along the ray to the closest occluder or surface of the field, infinite when there is
+/// none. Unlike the tiles, they are found analytically and by sphere tracing.
+fn obstacle_distance(world: &World, occluders: &[Occluder], ray_origin: Vec2, ray_direction: Vec2) -> f32 {
+    let occluder_distance = occluder::closest_entry(occluders, ray_origin, ray_direction);
+    let field_distance = world.field.trace(ray_origin, ray_direction).unwrap_or(f32::INFINITY);
+
+    return occluder_distance.min(field_distance);
+}
+
+/// Distance along the ray to the first solid tile it enters within `max_distance`, infinite when
+/// there is none. Walks the tiles from edge to edge like `World::raycast`, tiles outside the grid
+/// are solid.
+fn tile_distance(world: &World, ray_origin: Vec2, ray_direction: Vec2, max_distance: f32) -> f32 {
+    let mut tile = (ray_origin / World::TILE_SIZE).floor().as_ivec2();
+
+    if world.is_solid(tile) {
+        return 0.0;
+    }
+
+    let step = IVec2::new(ray_direction.x.signum() as i32, ray_direction.y.signum() as i32);
+
+    let mut next = Vec2::splat(f32::INFINITY);
+    let mut delta = Vec2::splat(f32::INFINITY);
+
+    for axis in 0..2 {
+        if ray_direction[axis] != 0.0 {
+            let edge = (tile[axis] + (step[axis] > 0) as i32) as f32 * World::TILE_SIZE;
+
+            next[axis] = (edge - ray_origin[axis]) / ray_direction[axis];
+            delta[axis] = World::TILE_SIZE / ray_direction[axis].abs();
+        }
+    }
+
+    for _ in 0..TILE_STEPS {
+        let axis = if next.x < next.y { 0 } else { 1 };
+        let distance = next[axis];
+
+        if distance > max_distance {
+            break;
+        }
+
+        tile[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        if world.is_solid(tile) {
+            return distance;
+        }
+    }
+
+    return f32::INFINITY;
+}
+
+pub fn sun_light(world: &World, occluders: &[Occluder], sky: &Sky, time_of_day: f32, ray_origin: Vec2) -> Vec3 {
+    let hit = ray_hit(world, occluders, ray_origin, -Sky::sun_direction(time_of_day));
+
+    if hit.hit {
+        return Vec3::ZERO;
+    }
+
+    return sky.sun_radiance(time_of_day);
+}
+
+pub fn sky_light(world: &World, occluders: &[Occluder], sky: &Sky, time_of_day: f32, ray_origin: Vec2) -> Vec3 {
+    let mut visibility = 0.0;
+
+    for i in 0..8 {
+        let angle = std::f32::consts::PI * (i as f32 + 0.5) / 8.0;
+        let ray_direction = Vec2::new(angle.cos(), angle.sin());
+
+        let hit = ray_hit(world, occluders, ray_origin, ray_direction);
+
+        if !hit.hit && hit.position.y >= World::HEIGHT as f32 * World::TILE_SIZE {
+            visibility += 1.0 / 8.0;
+        }
+    }
+
+    return sky.ambient_radiance(time_of_day) * visibility;
+}
+
+/// Light reaching the floor at a point of the world, the value `lighting` returns for it: what
+/// `surface_lighting` gives for a normal facing up.
+pub fn radiance(world: &World, occluders: &[Occluder], lights: &[PointLight], time_of_day: f32, ray_origin: Vec2) -> Vec3 {
+    if !World::contains(ray_origin) || world.is_solid(World::tile_at(ray_origin)) || world.field.is_solid(ray_origin) || occluders.iter().any(|occluder| occluder.shape.contains(ray_origin)) {
+        return Vec3::ZERO;
+    }
+
+    let mut result = Vec3::ZERO;
+
+    for light in lights {
+        let ray_direction = (ray_origin - light.position).normalize_or_zero();
+
+        let hit = ray_hit(world, occluders, ray_origin, ray_direction);
+
+        if hit.hit && ray_check(world, occluders, hit.position, light.position) {
+            let distance_1 = (ray_origin - hit.position).length();
+            let distance_2 = (hit.position - light.position).length();
+
+            let t_1 = light.falloff.attenuation(distance_1);
+            let t_2 = light.falloff.attenuation(distance_2);
+
+            result += light.radiance() * (t_1 * t_2) / 100.0;
+        }
+    }
+
+    if let Some(sky) = &world.sky {
+        result += sun_light(world, occluders, sky, time_of_day, ray_origin);
+        result += sky_light(world, occluders, sky, time_of_day, ray_origin);
+    }
+
+    return result;
+}
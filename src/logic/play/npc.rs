@@ -0,0 +1,273 @@
+use glam::{
+    IVec2,
+    Vec2,
+    Vec3,
+};
+
+use crate::logic::play::{
+    entity::Transform,
+    light::PointLight,
+    lighting,
+    occluder::Occluder,
+    pathfinding::{
+        self,
+        Pathfinder,
+    },
+    world::World,
+};
+
+/// What an agent perceives the level with, gathered once per simulation step.
+pub struct Surroundings<'a> {
+    pub world: &'a World,
+    pub occluders: &'a [Occluder],
+    /// Every light of the level, including the cursor light.
+    pub lights: &'a [PointLight],
+    pub time_of_day: f32,
+}
+
+impl<'a> Surroundings<'a> {
+    /// Luminance of the light reaching a position, from the CPU reference of the lighting, so
+    /// what looks dark on screen is dark for the agents too.
+    pub fn light_level(&self, position: Vec2) -> f32 {
+        let radiance = lighting::radiance(self.world, self.occluders, self.lights, self.time_of_day, position);
+
+        return radiance.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NpcState {
+    /// Walks its route.
+    Patrol,
+    /// Stopped, facing something it just caught sight of.
+    Alert,
+    /// Runs after the target while it sees it.
+    Chase,
+    /// Goes where the target was last seen and looks around.
+    Search,
+}
+
+/// Guard walking a patrol route, spotting the player with a cone of vision. The player is only
+/// noticed when lit, or when very close.
+pub struct Npc {
+    /// Unit vector the agent looks along.
+    pub facing: Vec2,
+
+    /// Points walked through in a loop while patrolling.
+    pub route: Vec<Vec2>,
+    /// Index in `route` of the point walked to.
+    pub waypoint: usize,
+
+    pub state: NpcState,
+    /// Seconds spent in the current state.
+    pub state_time: f32,
+    /// Where the target was seen for the last time.
+    pub last_seen: Option<Vec2>,
+
+    /// Light reaching the agent at the last step, see `Surroundings::light_level`.
+    pub light_level: f32,
+
+    /// Tile centers left to walk through towards `path_goal`.
+    path: Vec<Vec2>,
+    path_goal: Option<IVec2>,
+    /// `World::revision` the path was planned for.
+    path_revision: u64,
+}
+
+impl Npc {
+    const PATROL_SPEED: f32 = 60.0;
+    const CHASE_SPEED: f32 = 150.0;
+    const SEARCH_SPEED: f32 = 90.0;
+    /// Radians per second the agent turns while looking around.
+    const SCAN_SPEED: f32 = 2.0;
+
+    pub const VIEW_DISTANCE: f32 = 220.0;
+    /// Half the angle of the cone of vision, in radians.
+    pub const VIEW_HALF_ANGLE: f32 = 0.6;
+    /// Distance under which the target is noticed even in the dark.
+    const CLOSE_DISTANCE: f32 = 40.0;
+    /// Light level above which the target can be seen, and the agent counts as lit.
+    pub const LIGHT_THRESHOLD: f32 = 0.05;
+
+    /// Seconds the target must stay in sight before a chase, and spent searching.
+    const ALERT_TIME: f32 = 0.5;
+    const SEARCH_TIME: f32 = 4.0;
+
+    pub fn new(route: Vec<Vec2>) -> Self {
+        return Self {
+            facing: Vec2::X,
+
+            route,
+            waypoint: 0,
+
+            state: NpcState::Patrol,
+            state_time: 0.0,
+            last_seen: None,
+
+            light_level: 0.0,
+
+            path: Vec::new(),
+            path_goal: None,
+            path_revision: 0,
+        };
+    }
+
+    pub fn is_lit(&self) -> bool {
+        return self.light_level >= Self::LIGHT_THRESHOLD;
+    }
+
+    /// Points left to walk through, ending on the goal of the current walk.
+    pub fn path(&self) -> &[Vec2] {
+        return &self.path;
+    }
+
+    /// Whether a point lies in the cone of vision of the agent standing at `position`, with no
+    /// wall or occluder in between.
+    pub fn can_see(&self, position: Vec2, surroundings: &Surroundings, point: Vec2) -> bool {
+        let offset = point - position;
+        let distance = offset.length();
+
+        if distance > Self::VIEW_DISTANCE {
+            return false;
+        }
+
+        if distance > 0.0 && self.facing.angle_between(offset).abs() > Self::VIEW_HALF_ANGLE {
+            return false;
+        }
+
+        return surroundings.world.line_of_sight_with_occluders(surroundings.occluders, position, point);
+    }
+
+    /// Whether the target is noticed: in view and either lit or close.
+    fn spots(&self, position: Vec2, surroundings: &Surroundings, target: Vec2) -> bool {
+        if !self.can_see(position, surroundings, target) {
+            return false;
+        }
+
+        return position.distance(target) < Self::CLOSE_DISTANCE || surroundings.light_level(target) >= Self::LIGHT_THRESHOLD;
+    }
+
+    fn set_state(&mut self, state: NpcState) {
+        self.state = state;
+        self.state_time = 0.0;
+    }
+
+    pub fn update(&mut self, transform: &mut Transform, delta_time: f32, surroundings: &Surroundings, pathfinder: &mut Pathfinder, target: Vec2) {
+        self.state_time += delta_time;
+
+        self.light_level = surroundings.light_level(transform.position);
+
+        let spotted = self.spots(transform.position, surroundings, target);
+
+        if spotted {
+            self.last_seen = Some(target);
+        }
+
+        match self.state {
+            NpcState::Patrol => {
+                if spotted {
+                    self.set_state(NpcState::Alert);
+                } else if let Some(waypoint) = self.route.get(self.waypoint).copied() {
+                    if self.walk_to(transform, surroundings.world, pathfinder, waypoint, Self::PATROL_SPEED * delta_time) {
+                        self.waypoint = (self.waypoint + 1) % self.route.len();
+                    }
+                }
+            }
+            NpcState::Alert => {
+                if let Some(last_seen) = self.last_seen {
+                    self.face(transform.position, last_seen);
+                }
+
+                if !spotted {
+                    self.set_state(NpcState::Search);
+                } else if self.state_time >= Self::ALERT_TIME {
+                    self.set_state(NpcState::Chase);
+                }
+            }
+            NpcState::Chase => {
+                if !spotted {
+                    self.set_state(NpcState::Search);
+                } else {
+                    self.walk_to(transform, surroundings.world, pathfinder, target, Self::CHASE_SPEED * delta_time);
+                }
+            }
+            NpcState::Search => {
+                if spotted {
+                    self.set_state(NpcState::Chase);
+                } else if self.state_time >= Self::SEARCH_TIME {
+                    self.last_seen = None;
+                    self.set_state(NpcState::Patrol);
+                } else {
+                    let arrived = match self.last_seen {
+                        Some(last_seen) => self.walk_to(transform, surroundings.world, pathfinder, last_seen, Self::SEARCH_SPEED * delta_time),
+                        None => true,
+                    };
+
+                    if arrived {
+                        self.facing = Vec2::from_angle(Self::SCAN_SPEED * delta_time).rotate(self.facing);
+                    }
+                }
+            }
+        }
+    }
+
+    fn face(&mut self, position: Vec2, point: Vec2) {
+        let direction = (point - position).normalize_or_zero();
+
+        if direction != Vec2::ZERO {
+            self.facing = direction;
+        }
+    }
+
+    /// Moves up to `distance` along a path to `goal`, returning whether it was reached. The
+    /// agent stays in place when no path leads there, and plans again when the goal moves to
+    /// another tile or the tiles change.
+    fn walk_to(&mut self, transform: &mut Transform, world: &World, pathfinder: &mut Pathfinder, goal: Vec2, mut distance: f32) -> bool {
+        let goal_tile = (goal / World::TILE_SIZE).floor().as_ivec2();
+
+        if self.path_goal != Some(goal_tile) || self.path_revision != world.revision() {
+            let start = (transform.position / World::TILE_SIZE).floor().as_ivec2();
+
+            self.path_goal = Some(goal_tile);
+            self.path_revision = world.revision();
+            self.path = match pathfinder.path(world, start, goal_tile) {
+                // The first tile is the one the agent stands on, the last one is replaced by the goal
+                Some(tiles) => tiles.iter().skip(1).take(tiles.len().saturating_sub(2)).map(|tile| pathfinding::tile_center(*tile)).collect(),
+                None => {
+                    self.path_goal = None;
+
+                    return false;
+                }
+            };
+            self.path.push(goal);
+        }
+
+        // The goal may move within its tile, like a chased target
+        if let Some(last) = self.path.last_mut() {
+            *last = goal;
+        }
+
+        while let Some(next) = self.path.first().copied() {
+            let offset = next - transform.position;
+            let length = offset.length();
+
+            if length > 0.0 {
+                self.facing = offset / length;
+            }
+
+            if length > distance {
+                transform.position += offset / length * distance;
+
+                return false;
+            }
+
+            transform.position = next;
+            distance -= length;
+            self.path.remove(0);
+        }
+
+        self.path_goal = None;
+
+        return true;
+    }
+}
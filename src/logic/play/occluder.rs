@@ -0,0 +1,186 @@
+use glam::{
+    Vec2,
+    Vec3,
+};
+
+/// Outline of a dynamic occluder in world space.
+#[derive(Clone)]
+pub enum Shape {
+    Circle { center: Vec2, radius: f32 },
+    Aabb { min: Vec2, max: Vec2 },
+    /// Convex, counter-clockwise vertices. Only the first `MAX_POLYGON_VERTICES` reach the GPU, and
+    /// a polygon of less than 3 vertices is empty.
+    Polygon(Vec<Vec2>),
+}
+
+impl Shape {
+    pub const MAX_POLYGON_VERTICES: usize = 8;
+
+    /// Identifier of the shape on the GPU side.
+    pub fn kind(&self) -> u32 {
+        return match self {
+            Shape::Circle { .. } => 0,
+            Shape::Aabb { .. } => 1,
+            Shape::Polygon(_) => 2,
+        };
+    }
+
+    /// Distance along the ray at which it enters the shape, if it does so ahead of its origin.
+    /// Mirrors `occluder_entry` in `lighting.wgsl`.
+    pub fn entry(&self, ray_origin: Vec2, ray_direction: Vec2) -> Option<f32> {
+        let (entry, exit) = match self {
+            Shape::Circle { center, radius } => {
+                let offset = ray_origin - *center;
+                let b = offset.dot(ray_direction);
+                let c = offset.length_squared() - radius * radius;
+                let discriminant = b * b - c;
+
+                if discriminant < 0.0 {
+                    return None;
+                }
+
+                (-b - discriminant.sqrt(), -b + discriminant.sqrt())
+            }
+            Shape::Aabb { min, max } => {
+                let mut entry = f32::NEG_INFINITY;
+                let mut exit = f32::INFINITY;
+
+                for axis in 0..2 {
+                    if ray_direction[axis] == 0.0 {
+                        if ray_origin[axis] < min[axis] || ray_origin[axis] > max[axis] {
+                            return None;
+                        }
+
+                        continue;
+                    }
+
+                    let t_1 = (min[axis] - ray_origin[axis]) / ray_direction[axis];
+                    let t_2 = (max[axis] - ray_origin[axis]) / ray_direction[axis];
+
+                    entry = entry.max(t_1.min(t_2));
+                    exit = exit.min(t_1.max(t_2));
+                }
+
+                (entry, exit)
+            }
+            Shape::Polygon(vertices) => {
+                if vertices.len() < 3 {
+                    return None;
+                }
+
+                let mut entry = f32::NEG_INFINITY;
+                let mut exit = f32::INFINITY;
+
+                // Clips the ray against the half plane inside each edge
+                for (a, normal) in Self::edges(vertices) {
+                    let denominator = normal.dot(ray_direction);
+                    let numerator = normal.dot(a - ray_origin);
+
+                    if denominator == 0.0 {
+                        if numerator < 0.0 {
+                            return None;
+                        }
+                    } else if denominator > 0.0 {
+                        exit = exit.min(numerator / denominator);
+                    } else {
+                        entry = entry.max(numerator / denominator);
+                    }
+                }
+
+                (entry, exit)
+            }
+        };
+
+        if entry > exit || entry <= 0.0 {
+            return None;
+        }
+
+        return Some(entry);
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        return match self {
+            Shape::Circle { center, radius } => position.distance_squared(*center) < radius * radius,
+            Shape::Aabb { min, max } => position.cmpge(*min).all() && position.cmplt(*max).all(),
+            Shape::Polygon(vertices) => vertices.len() >= 3 && Self::edges(vertices).all(|(a, normal)| normal.dot(position - a) < 0.0),
+        };
+    }
+
+    /// Unit vector pointing out of the shape at a point of its outline.
+    pub fn normal(&self, position: Vec2) -> Vec2 {
+        return match self {
+            Shape::Circle { center, .. } => (position - *center).normalize_or_zero(),
+            Shape::Aabb { min, max } => {
+                // Face closest to the point
+                let faces = [
+                    (position.x - min.x, Vec2::NEG_X),
+                    (max.x - position.x, Vec2::X),
+                    (position.y - min.y, Vec2::NEG_Y),
+                    (max.y - position.y, Vec2::Y),
+                ];
+
+                faces.into_iter().min_by(|a, b| a.0.abs().total_cmp(&b.0.abs())).map_or(Vec2::ZERO, |(_, normal)| normal)
+            }
+            Shape::Polygon(vertices) => Self::edges(vertices)
+                .map(|(a, normal)| (normal.normalize_or_zero().dot(position - a), normal.normalize_or_zero()))
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map_or(Vec2::ZERO, |(_, normal)| normal),
+        };
+    }
+
+    /// First vertex and outward normal of each edge of a counter-clockwise polygon.
+    fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let count = vertices.len().min(Self::MAX_POLYGON_VERTICES);
+
+        return (0..count).map(move |i| {
+            let a = vertices[i];
+            let edge = vertices[(i + 1) % count] - a;
+
+            return (a, Vec2::new(edge.y, -edge.x));
+        });
+    }
+
+    /// Shape in between `self` and `next`, which must be the same kind of shape.
+    pub fn interpolate(&self, next: &Shape, alpha: f32) -> Shape {
+        return match (self, next) {
+            (Shape::Circle { center, radius }, Shape::Circle { center: next_center, radius: next_radius }) => Shape::Circle {
+                center: center.lerp(*next_center, alpha),
+                radius: radius + (next_radius - radius) * alpha,
+            },
+            (Shape::Aabb { min, max }, Shape::Aabb { min: next_min, max: next_max }) => Shape::Aabb {
+                min: min.lerp(*next_min, alpha),
+                max: max.lerp(*next_max, alpha),
+            },
+            (Shape::Polygon(vertices), Shape::Polygon(next_vertices)) if vertices.len() == next_vertices.len() => Shape::Polygon(
+                vertices.iter().zip(next_vertices).map(|(vertex, next_vertex)| vertex.lerp(*next_vertex, alpha)).collect(),
+            ),
+            _ => next.clone(),
+        };
+    }
+}
+
+/// Moving shape blocking the light like a solid tile, e.g. a character, a door or a crate.
+#[derive(Clone)]
+pub struct Occluder {
+    pub shape: Shape,
+
+    /// Linear RGB of the unlit surface.
+    pub color: Vec3,
+}
+
+impl Occluder {
+    pub fn new(shape: Shape, color: Vec3) -> Self {
+        return Self {
+            shape,
+
+            color,
+        };
+    }
+}
+
+/// Distance along the ray to the closest occluder it enters, infinite when there is none.
+pub fn closest_entry(occluders: &[Occluder], ray_origin: Vec2, ray_direction: Vec2) -> f32 {
+    return occluders.iter()
+        .filter_map(|occluder| occluder.shape.entry(ray_origin, ray_direction))
+        .fold(f32::INFINITY, f32::min);
+}
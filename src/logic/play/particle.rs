@@ -0,0 +1,193 @@
+use glam::{
+    Vec2,
+    Vec3,
+};
+
+use crate::logic::play::light::{
+    Falloff,
+    PointLight,
+};
+
+/// Short lived point moving on its own, drawn at the transform of its entity.
+#[derive(Clone, Copy)]
+pub struct Particle {
+    /// World units per second.
+    pub velocity: Vec2,
+    /// Acceleration in world units per second squared, e.g. gravity.
+    pub acceleration: Vec2,
+
+    /// Seconds since the particle was spawned, it is despawned once `age` reaches `lifetime`.
+    pub age: f32,
+    pub lifetime: f32,
+
+    /// Linear RGB.
+    pub color: Vec3,
+    /// Half size in world units.
+    pub size: f32,
+    /// Change of the half size in world units per second, e.g. smoke spreading out.
+    pub growth: f32,
+
+    /// Scale of the color the particle glows with on its own, 0 for particles only lit by the
+    /// scene.
+    pub emission: f32,
+    /// Whether the glow also lights the scene, see `light`.
+    pub casts_light: bool,
+}
+
+impl Particle {
+    /// Share of the velocity kept when bouncing off a wall.
+    pub const RESTITUTION: f32 = 0.4;
+
+    /// Intensity of the light of a particle glowing with an emission of 1.
+    const LIGHT_INTENSITY: f32 = 8.0;
+    const LIGHT_RADIUS: f32 = 60.0;
+
+    pub fn new(velocity: Vec2, lifetime: f32, color: Vec3, size: f32) -> Self {
+        return Self {
+            velocity,
+            acceleration: Vec2::ZERO,
+
+            age: 0.0,
+            lifetime,
+
+            color,
+            size,
+            growth: 0.0,
+
+            emission: 0.0,
+            casts_light: false,
+        };
+    }
+
+    /// Share of the lifetime already spent, in `[0, 1]`.
+    pub fn progress(&self) -> f32 {
+        return (self.age / self.lifetime.max(f32::EPSILON)).clamp(0.0, 1.0);
+    }
+
+    pub fn is_expired(&self) -> bool {
+        return self.age >= self.lifetime;
+    }
+
+    /// Opacity, fading out over the lifetime.
+    pub fn opacity(&self) -> f32 {
+        return 1.0 - self.progress();
+    }
+
+    /// Light of the particle standing at `position`, fading out with it. `None` when it doesn't
+    /// light the scene.
+    pub fn light(&self, position: Vec2) -> Option<PointLight> {
+        if !self.casts_light || self.emission <= 0.0 {
+            return None;
+        }
+
+        return Some(PointLight::new(position, self.color, Self::LIGHT_INTENSITY * self.emission * self.opacity(), Falloff::Smooth { radius: Self::LIGHT_RADIUS }));
+    }
+}
+
+/// Spawns particles at the transform of its entity, with randomized directions, speeds and
+/// lifetimes.
+#[derive(Clone, Copy)]
+pub struct Emitter {
+    /// Particles per second.
+    pub rate: f32,
+
+    /// Unit vector the particles are thrown along, turned by up to `spread` radians either way.
+    pub direction: Vec2,
+    pub spread: f32,
+    /// Range of the initial speed, in world units per second.
+    pub speed: (f32, f32),
+    /// Range of the lifetime, in seconds.
+    pub lifetime: (f32, f32),
+
+    /// Every particle spawned is a copy of it with a new velocity and lifetime.
+    pub particle: Particle,
+
+    /// Particles owed since the last one was spawned.
+    accumulator: f32,
+    /// State of the random generator.
+    seed: u32,
+}
+
+impl Emitter {
+    pub fn new(rate: f32, direction: Vec2, spread: f32, speed: (f32, f32), lifetime: (f32, f32), particle: Particle) -> Self {
+        return Self {
+            rate,
+
+            direction: direction.normalize_or_zero(),
+            spread,
+            speed,
+            lifetime,
+
+            particle,
+
+            accumulator: 0.0,
+            seed: 0x9e37_79b9,
+        };
+    }
+
+    /// Fast glowing sparks falling down and bouncing off the walls, lighting their surroundings.
+    pub fn sparks(direction: Vec2) -> Self {
+        let particle = Particle {
+            acceleration: Vec2::new(0.0, -300.0),
+            emission: 4.0,
+            casts_light: true,
+            ..Particle::new(Vec2::ZERO, 0.0, Vec3::new(1.0, 0.55, 0.15), 1.5)
+        };
+
+        return Self::new(30.0, direction, 0.5, (120.0, 220.0), (0.6, 1.2), particle);
+    }
+
+    /// Slow specks floating around, only seen where the scene is lit.
+    pub fn dust() -> Self {
+        let particle = Particle::new(Vec2::ZERO, 0.0, Vec3::new(0.8, 0.75, 0.65), 1.0);
+
+        return Self::new(6.0, Vec2::Y, std::f32::consts::PI, (4.0, 12.0), (3.0, 6.0), particle);
+    }
+
+    /// Large puffs rising and spreading out.
+    pub fn smoke() -> Self {
+        let particle = Particle {
+            acceleration: Vec2::new(0.0, 10.0),
+            growth: 6.0,
+            ..Particle::new(Vec2::ZERO, 0.0, Vec3::new(0.35, 0.35, 0.37), 4.0)
+        };
+
+        return Self::new(8.0, Vec2::Y, 0.4, (15.0, 30.0), (2.0, 3.5), particle);
+    }
+
+    /// Xorshift, uniform in `[0, 1)`.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        return (self.seed >> 8) as f32 / (1 << 24) as f32;
+    }
+
+    fn random_range(&mut self, (min, max): (f32, f32)) -> f32 {
+        return min + (max - min) * self.random();
+    }
+
+    /// Particles due after `delta_time` seconds more.
+    pub fn emit(&mut self, delta_time: f32) -> Vec<Particle> {
+        self.accumulator += self.rate * delta_time;
+
+        let count = self.accumulator.floor();
+        self.accumulator -= count;
+
+        let mut particles = Vec::with_capacity(count as usize);
+
+        for _ in 0..count as usize {
+            let angle = (self.random() * 2.0 - 1.0) * self.spread;
+            let speed = self.random_range(self.speed);
+
+            particles.push(Particle {
+                velocity: Vec2::from_angle(angle).rotate(self.direction) * speed,
+                lifetime: self.random_range(self.lifetime),
+                ..self.particle
+            });
+        }
+
+        return particles;
+    }
+}
@@ -0,0 +1,489 @@
+use std::cmp::Ordering;
+use std::collections::{
+    BinaryHeap,
+    HashMap,
+};
+
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use crate::logic::play::world::World;
+
+/// Whether paths may step diagonally between tiles, and past which walls.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiagonalMovement {
+    Never,
+    /// Diagonal steps need both tiles beside them free, so paths never touch a wall corner.
+    NoCornerCutting,
+    /// Diagonal steps need one of the tiles beside them free, paths cut around wall corners.
+    CutCorners,
+    /// Diagonal steps are allowed even between two walls.
+    Always,
+}
+
+impl DiagonalMovement {
+    /// Steps to the neighbours of `tile` that can be walked to, with their cost.
+    fn steps(&self, world: &World, tile: IVec2) -> Vec<(IVec2, f32)> {
+        let mut steps = Vec::with_capacity(8);
+
+        for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            if !world.is_solid(tile + offset) {
+                steps.push((tile + offset, 1.0));
+            }
+        }
+
+        if *self == DiagonalMovement::Never {
+            return steps;
+        }
+
+        for offset in [IVec2::new(1, 1), IVec2::new(1, -1), IVec2::new(-1, 1), IVec2::new(-1, -1)] {
+            if world.is_solid(tile + offset) {
+                continue;
+            }
+
+            let free_sides = [IVec2::new(offset.x, 0), IVec2::new(0, offset.y)].iter()
+                .filter(|side| !world.is_solid(tile + **side))
+                .count();
+
+            let allowed = match self {
+                DiagonalMovement::Never => false,
+                DiagonalMovement::NoCornerCutting => free_sides == 2,
+                DiagonalMovement::CutCorners => free_sides >= 1,
+                DiagonalMovement::Always => true,
+            };
+
+            if allowed {
+                steps.push((tile + offset, std::f32::consts::SQRT_2));
+            }
+        }
+
+        return steps;
+    }
+
+    /// Admissible estimate of the cost between two tiles.
+    fn heuristic(&self, a: IVec2, b: IVec2) -> f32 {
+        let delta = (a - b).abs();
+
+        if *self == DiagonalMovement::Never {
+            return (delta.x + delta.y) as f32;
+        }
+
+        let diagonal = delta.x.min(delta.y) as f32;
+        let straight = (delta.x - delta.y).abs() as f32;
+
+        return diagonal * std::f32::consts::SQRT_2 + straight;
+    }
+}
+
+/// Tile waiting in the open set, ordered so the heap pops the lowest cost first.
+struct Candidate {
+    tile: IVec2,
+    cost: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cost == other.cost;
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other.cost.total_cmp(&self.cost);
+    }
+}
+
+/// Index of a tile the searches reached, always inside the grid since the outside is solid.
+fn index(tile: IVec2) -> usize {
+    return World::index(tile).expect("Tile outside the grid");
+}
+
+/// Center of a tile in world space.
+pub fn tile_center(tile: IVec2) -> Vec2 {
+    return (tile.as_vec2() + 0.5) * World::TILE_SIZE;
+}
+
+/// Shortest path between two tiles with A*, both ends included. `None` when either end is solid
+/// or the goal can't be reached.
+pub fn find_path(world: &World, start: IVec2, goal: IVec2, diagonal: DiagonalMovement) -> Option<Vec<IVec2>> {
+    if world.is_solid(start) || world.is_solid(goal) {
+        return None;
+    }
+
+    let mut costs = vec![f32::INFINITY; World::HEIGHT * World::WIDTH];
+    let mut came_from = vec![None; World::HEIGHT * World::WIDTH];
+    let mut open = BinaryHeap::new();
+
+    costs[index(start)] = 0.0;
+    open.push(Candidate { tile: start, cost: diagonal.heuristic(start, goal) });
+
+    while let Some(Candidate { tile, cost }) = open.pop() {
+        if tile == goal {
+            let mut path = vec![goal];
+
+            while let Some(previous) = came_from[index(*path.last().unwrap())] {
+                path.push(previous);
+            }
+
+            path.reverse();
+
+            return Some(path);
+        }
+
+        // Stale entry, the tile was reached more cheaply since it was queued
+        if cost > costs[index(tile)] + diagonal.heuristic(tile, goal) {
+            continue;
+        }
+
+        for (neighbour, step_cost) in diagonal.steps(world, tile) {
+            let neighbour_cost = costs[index(tile)] + step_cost;
+
+            if neighbour_cost < costs[index(neighbour)] {
+                costs[index(neighbour)] = neighbour_cost;
+                came_from[index(neighbour)] = Some(tile);
+
+                open.push(Candidate { tile: neighbour, cost: neighbour_cost + diagonal.heuristic(neighbour, goal) });
+            }
+        }
+    }
+
+    return None;
+}
+
+/// Cost from every tile to a single goal, for many agents heading to it: each one follows the
+/// direction of its tile instead of searching its own path.
+pub struct FlowField {
+    pub goal: IVec2,
+    pub diagonal: DiagonalMovement,
+
+    /// Column major like the tiles, infinite where the goal can't be reached.
+    costs: Vec<f32>,
+}
+
+impl FlowField {
+    /// Runs Dijkstra outwards from the goal over the whole grid.
+    pub fn new(world: &World, goal: IVec2, diagonal: DiagonalMovement) -> Self {
+        let mut costs = vec![f32::INFINITY; World::HEIGHT * World::WIDTH];
+        let mut open = BinaryHeap::new();
+
+        if !world.is_solid(goal) {
+            costs[index(goal)] = 0.0;
+            open.push(Candidate { tile: goal, cost: 0.0 });
+        }
+
+        while let Some(Candidate { tile, cost }) = open.pop() {
+            if cost > costs[index(tile)] {
+                continue;
+            }
+
+            // The moves are symmetric, walking from a neighbour to this tile costs the same
+            for (neighbour, step_cost) in diagonal.steps(world, tile) {
+                let neighbour_cost = cost + step_cost;
+
+                if neighbour_cost < costs[index(neighbour)] {
+                    costs[index(neighbour)] = neighbour_cost;
+
+                    open.push(Candidate { tile: neighbour, cost: neighbour_cost });
+                }
+            }
+        }
+
+        return Self {
+            goal,
+            diagonal,
+
+            costs,
+        };
+    }
+
+    /// Cost of the shortest path from the tile to the goal, infinite when there is none.
+    pub fn cost(&self, tile: IVec2) -> f32 {
+        return World::index(tile).map_or(f32::INFINITY, |index| self.costs[index]);
+    }
+
+    /// Neighbour of the tile closest to the goal, `None` on the goal and where it can't be reached.
+    pub fn next_tile(&self, world: &World, tile: IVec2) -> Option<IVec2> {
+        if tile == self.goal || self.cost(tile).is_infinite() {
+            return None;
+        }
+
+        return self.diagonal.steps(world, tile)
+            .into_iter()
+            .map(|(neighbour, step_cost)| (neighbour, step_cost + self.cost(neighbour)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(neighbour, _)| neighbour);
+    }
+
+    /// Unit direction to follow from a position in world space, towards the center of the next
+    /// tile, or of the goal once on it.
+    pub fn direction(&self, world: &World, position: Vec2) -> Vec2 {
+        let tile = (position / World::TILE_SIZE).floor().as_ivec2();
+
+        let target = match self.next_tile(world, tile) {
+            Some(next) => tile_center(next),
+            None if tile == self.goal => tile_center(self.goal),
+            None => return Vec2::ZERO,
+        };
+
+        return (target - position).normalize_or_zero();
+    }
+}
+
+/// Paths and flow fields computed on the tiles, kept until a tile or the movement rule changes.
+pub struct Pathfinder {
+    diagonal: DiagonalMovement,
+
+    /// `World::revision` the cached results were computed for.
+    revision: u64,
+    paths: HashMap<(IVec2, IVec2), Option<Vec<IVec2>>>,
+    flow_fields: HashMap<IVec2, FlowField>,
+}
+
+impl Pathfinder {
+    /// Results kept at most, the caches are emptied when they grow past it.
+    const MAX_PATHS: usize = 256;
+    const MAX_FLOW_FIELDS: usize = 8;
+
+    pub fn new(diagonal: DiagonalMovement) -> Self {
+        return Self {
+            diagonal,
+
+            revision: 0,
+            paths: HashMap::new(),
+            flow_fields: HashMap::new(),
+        };
+    }
+
+    pub fn diagonal(&self) -> DiagonalMovement {
+        return self.diagonal;
+    }
+
+    /// Changes the movement rule, dropping the results computed with the previous one.
+    pub fn set_diagonal(&mut self, diagonal: DiagonalMovement) {
+        if diagonal != self.diagonal {
+            self.diagonal = diagonal;
+            self.paths.clear();
+            self.flow_fields.clear();
+        }
+    }
+
+    /// Drops the results computed on older tiles.
+    fn validate(&mut self, world: &World) {
+        if world.revision() != self.revision {
+            self.revision = world.revision();
+            self.paths.clear();
+            self.flow_fields.clear();
+        }
+    }
+
+    /// Shortest path between two tiles, see `find_path`.
+    pub fn path(&mut self, world: &World, start: IVec2, goal: IVec2) -> Option<&[IVec2]> {
+        self.validate(world);
+
+        if self.paths.len() >= Self::MAX_PATHS && !self.paths.contains_key(&(start, goal)) {
+            self.paths.clear();
+        }
+
+        let diagonal = self.diagonal;
+
+        return self.paths.entry((start, goal))
+            .or_insert_with(|| find_path(world, start, goal, diagonal))
+            .as_deref();
+    }
+
+    pub fn flow_field(&mut self, world: &World, goal: IVec2) -> &FlowField {
+        self.validate(world);
+
+        if self.flow_fields.len() >= Self::MAX_FLOW_FIELDS && !self.flow_fields.contains_key(&goal) {
+            self.flow_fields.clear();
+        }
+
+        let diagonal = self.diagonal;
+
+        return self.flow_fields.entry(goal).or_insert_with(|| FlowField::new(world, goal, diagonal));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec2;
+
+    use crate::logic::play::world::World;
+
+    use super::{
+        find_path,
+        DiagonalMovement,
+        FlowField,
+        Pathfinder,
+    };
+
+    const RULES: [DiagonalMovement; 4] = [
+        DiagonalMovement::Never,
+        DiagonalMovement::NoCornerCutting,
+        DiagonalMovement::CutCorners,
+        DiagonalMovement::Always,
+    ];
+
+    /// Level with only its border walls, and walls on `walls`.
+    fn world(walls: &[IVec2]) -> World {
+        let mut world = World::new();
+
+        for x in 1..World::WIDTH as i32 - 1 {
+            for y in 1..World::HEIGHT as i32 - 1 {
+                world.set_tile(IVec2::new(x, y), 0);
+            }
+        }
+
+        for wall in walls {
+            world.set_tile(*wall, 1);
+        }
+
+        return world;
+    }
+
+    /// Cost of a path, checking that every step is allowed by the rule.
+    fn cost(world: &World, path: &[IVec2], diagonal: DiagonalMovement) -> f32 {
+        let mut cost = 0.0;
+
+        for step in path.windows(2) {
+            let offset = step[1] - step[0];
+
+            assert!(!world.is_solid(step[1]), "step into the wall {}", step[1]);
+            assert!(offset.x.abs() <= 1 && offset.y.abs() <= 1 && offset != IVec2::ZERO, "step from {} to {}", step[0], step[1]);
+
+            if offset.x != 0 && offset.y != 0 {
+                let free_sides = [IVec2::new(offset.x, 0), IVec2::new(0, offset.y)].iter()
+                    .filter(|side| !world.is_solid(step[0] + **side))
+                    .count();
+
+                let allowed = match diagonal {
+                    DiagonalMovement::Never => false,
+                    DiagonalMovement::NoCornerCutting => free_sides == 2,
+                    DiagonalMovement::CutCorners => free_sides >= 1,
+                    DiagonalMovement::Always => true,
+                };
+
+                assert!(allowed, "diagonal step from {} to {} with {} free sides", step[0], step[1], free_sides);
+
+                cost += std::f32::consts::SQRT_2;
+            } else {
+                cost += 1.0;
+            }
+        }
+
+        return cost;
+    }
+
+    #[test]
+    fn path_around_a_corner() {
+        // The diagonal step between start and goal passes the corner of one wall
+        let world = world(&[IVec2::new(5, 5)]);
+        let (start, goal) = (IVec2::new(4, 5), IVec2::new(5, 4));
+
+        let expected = [2.0, 2.0, std::f32::consts::SQRT_2, std::f32::consts::SQRT_2];
+
+        for (diagonal, expected) in RULES.into_iter().zip(expected) {
+            let path = find_path(&world, start, goal, diagonal).unwrap();
+
+            assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+            assert!((cost(&world, &path, diagonal) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn path_between_two_walls() {
+        // The diagonal step between start and goal squeezes between two walls
+        let world = world(&[IVec2::new(5, 5), IVec2::new(4, 4)]);
+        let (start, goal) = (IVec2::new(4, 5), IVec2::new(5, 4));
+
+        for diagonal in RULES {
+            let path = find_path(&world, start, goal, diagonal).unwrap();
+            let cost = cost(&world, &path, diagonal);
+
+            if diagonal == DiagonalMovement::Always {
+                assert!((cost - std::f32::consts::SQRT_2).abs() < 1e-4);
+            } else {
+                assert!(cost > 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn walled_off_goal() {
+        let goal = IVec2::new(10, 10);
+        let walls: Vec<IVec2> = (-1..=1).flat_map(|x| (-1..=1).map(move |y| goal + IVec2::new(x, y))).filter(|tile| *tile != goal).collect();
+        let world = world(&walls);
+
+        for diagonal in RULES {
+            assert!(find_path(&world, IVec2::new(3, 3), goal, diagonal).is_none());
+
+            let flow_field = FlowField::new(&world, goal, diagonal);
+
+            assert!(flow_field.cost(IVec2::new(3, 3)).is_infinite());
+            assert_eq!(flow_field.next_tile(&world, IVec2::new(3, 3)), None);
+        }
+
+        assert!(find_path(&world, IVec2::new(3, 3), IVec2::new(0, 0), DiagonalMovement::Always).is_none());
+    }
+
+    #[test]
+    fn flow_field_matches_find_path() {
+        let world = World::new();
+        let goal = IVec2::new(12, 15);
+
+        for diagonal in RULES {
+            let flow_field = FlowField::new(&world, goal, diagonal);
+
+            for start in [IVec2::new(38, 15), IVec2::new(25, 4), IVec2::new(25, 26), IVec2::new(14, 8)] {
+                let path_cost = cost(&world, &find_path(&world, start, goal, diagonal).unwrap(), diagonal);
+
+                assert!((flow_field.cost(start) - path_cost).abs() < 1e-3, "{} from {}", flow_field.cost(start), start);
+
+                // Following the field costs as much as the shortest path
+                let mut path = vec![start];
+
+                while let Some(next) = flow_field.next_tile(&world, *path.last().unwrap()) {
+                    path.push(next);
+                }
+
+                assert_eq!(*path.last().unwrap(), goal);
+                assert!((cost(&world, &path, diagonal) - path_cost).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn caches_follow_the_tiles_and_the_rule() {
+        let mut world = world(&[]);
+        let mut pathfinder = Pathfinder::new(DiagonalMovement::NoCornerCutting);
+        let (start, goal) = (IVec2::new(3, 10), IVec2::new(13, 10));
+
+        assert_eq!(pathfinder.path(&world, start, goal).unwrap().len(), 11);
+        assert_eq!(pathfinder.flow_field(&world, goal).cost(start), 10.0);
+
+        world.set_tile(IVec2::new(8, 10), 1);
+
+        let path = pathfinder.path(&world, start, goal).unwrap().to_vec();
+
+        assert!(!path.contains(&IVec2::new(8, 10)));
+        assert!((pathfinder.flow_field(&world, goal).cost(start) - cost(&world, &path, DiagonalMovement::NoCornerCutting)).abs() < 1e-4);
+
+        pathfinder.set_diagonal(DiagonalMovement::Never);
+
+        let path = pathfinder.path(&world, start, goal).unwrap().to_vec();
+
+        assert!(pathfinder.diagonal() == DiagonalMovement::Never);
+        assert_eq!(cost(&world, &path, DiagonalMovement::Never), 12.0);
+        assert_eq!(pathfinder.flow_field(&world, goal).cost(start), 12.0);
+    }
+}
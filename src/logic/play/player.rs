@@ -0,0 +1,348 @@
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use crate::input::Input;
+use crate::logic::play::entity::Transform;
+use crate::logic::play::sdf::Field;
+use crate::logic::play::world::World;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Movement {
+    /// Side view with gravity, the player walks and jumps.
+    Platformer,
+    /// View from above, the player moves freely in every direction.
+    TopDown,
+}
+
+/// Character controlled by the input, its transform is the center of its collider.
+pub struct Player {
+    /// World units per second.
+    pub velocity: Vec2,
+    /// Half size of the axis aligned collider.
+    pub half_size: Vec2,
+
+    pub movement: Movement,
+    /// Whether the player stood on a solid tile or on the field at the end of the last step.
+    pub on_ground: bool,
+}
+
+impl Player {
+    const WALK_SPEED: f32 = 220.0;
+    /// Velocity change per second while steering, and while stopping.
+    const ACCELERATION: f32 = 1800.0;
+    const GRAVITY: f32 = 1400.0;
+    const JUMP_SPEED: f32 = 520.0;
+    const MAX_FALL_SPEED: f32 = 900.0;
+
+    /// Distance kept between the collider and the tiles, so touching a wall isn't overlapping it.
+    const SKIN: f32 = 1e-3;
+    /// Longest move between two tests against the field, so the collider can't jump over its
+    /// thinnest shapes.
+    const FIELD_STEP: f32 = 2.0 * Field::SURFACE;
+    /// Distance between the points of the outline of the collider tested against the field.
+    const FIELD_SAMPLE_SPACING: f32 = 2.0;
+    /// Halvings refining where the collider stops against the field.
+    const FIELD_REFINEMENTS: usize = 4;
+
+    pub fn new() -> Self {
+        return Self {
+            velocity: Vec2::ZERO,
+            half_size: Vec2::new(6.0, 9.0),
+
+            movement: Movement::Platformer,
+            on_ground: false,
+        };
+    }
+
+    pub fn update(&mut self, transform: &mut Transform, delta_time: f32, world: &World, input: &Input) {
+        let direction = Vec2::new(input.action_axis("move_left", "move_right"), input.action_axis("move_down", "move_up"));
+
+        match self.movement {
+            Movement::Platformer => {
+                self.velocity.x = Self::approach(self.velocity.x, direction.x * Self::WALK_SPEED, Self::ACCELERATION * delta_time);
+                self.velocity.y = (self.velocity.y - Self::GRAVITY * delta_time).max(-Self::MAX_FALL_SPEED);
+
+                if self.on_ground && input.action_pressed("jump") {
+                    self.velocity.y = Self::JUMP_SPEED;
+                }
+            }
+            Movement::TopDown => {
+                let target = direction.normalize_or_zero() * Self::WALK_SPEED;
+                let change = target - self.velocity;
+                let max_change = Self::ACCELERATION * delta_time;
+
+                self.velocity += change.clamp_length_max(max_change);
+            }
+        }
+
+        self.move_and_collide(transform, world, self.velocity * delta_time);
+    }
+
+    fn approach(value: f32, target: f32, max_change: f32) -> f32 {
+        return value + (target - value).clamp(-max_change, max_change);
+    }
+
+    /// Moves along each axis in turn, stopping at the first solid tile crossed or at the field, so
+    /// the player slides along walls and can't tunnel whatever its speed.
+    fn move_and_collide(&mut self, transform: &mut Transform, world: &World, delta: Vec2) {
+        self.on_ground = false;
+
+        for axis in 0..2 {
+            let allowed = self.sweep(transform.position, world, axis, delta[axis]);
+            let allowed = self.sweep_field(transform.position, &world.field, axis, allowed);
+
+            transform.position[axis] += allowed;
+
+            if allowed != delta[axis] {
+                if axis == 1 && delta.y < 0.0 {
+                    self.on_ground = true;
+                }
+
+                self.velocity[axis] = 0.0;
+            }
+        }
+    }
+
+    /// Distance the collider centered on `position` can travel along `axis`, up to `delta`.
+    fn sweep(&self, position: Vec2, world: &World, axis: usize, delta: f32) -> f32 {
+        if delta == 0.0 {
+            return 0.0;
+        }
+
+        let other = 1 - axis;
+        let min = position - self.half_size;
+        let max = position + self.half_size;
+
+        // Tiles covered across the movement
+        let first = Self::first_tile(min[other]);
+        let last = Self::last_tile(max[other]);
+
+        let is_blocking = |index: i32| (first..=last).any(|other_index| {
+            let mut tile = IVec2::ZERO;
+            tile[axis] = index;
+            tile[other] = other_index;
+
+            return world.is_solid(tile);
+        });
+
+        if delta > 0.0 {
+            for index in Self::last_tile(max[axis]) + 1..=Self::last_tile(max[axis] + delta) {
+                if is_blocking(index) {
+                    return (index as f32 * World::TILE_SIZE - Self::SKIN - max[axis]).clamp(0.0, delta);
+                }
+            }
+        } else {
+            for index in (Self::first_tile(min[axis] + delta)..Self::first_tile(min[axis])).rev() {
+                if is_blocking(index) {
+                    return ((index + 1) as f32 * World::TILE_SIZE + Self::SKIN - min[axis]).clamp(delta, 0.0);
+                }
+            }
+        }
+
+        return delta;
+    }
+
+    /// Distance the collider centered on `position` can travel along `axis` before it overlaps the
+    /// field, up to `delta`. A collider already overlapping it, like after an edit of the level,
+    /// may only move out.
+    fn sweep_field(&self, position: Vec2, field: &Field, axis: usize, delta: f32) -> f32 {
+        if delta == 0.0 {
+            return 0.0;
+        }
+
+        // Nothing of the field within reach of the collider
+        if field.distance(position) > self.half_size.length() + delta.abs() + Field::SURFACE {
+            return delta;
+        }
+
+        let moved = |travel: f32| {
+            let mut moved = position;
+            moved[axis] += travel;
+
+            return self.field_distance(moved, field);
+        };
+
+        let steps = (delta.abs() / Self::FIELD_STEP).ceil() as usize;
+        let mut distance = moved(0.0);
+        let mut allowed = 0.0;
+
+        for step in 1..=steps {
+            let travel = delta * step as f32 / steps as f32;
+            let travel_distance = moved(travel);
+
+            if travel_distance < Field::SURFACE && travel_distance < distance {
+                // Stops between the last free position and the blocked one
+                let mut blocked = travel;
+
+                for _ in 0..Self::FIELD_REFINEMENTS {
+                    let middle = (allowed + blocked) / 2.0;
+                    let middle_distance = moved(middle);
+
+                    if middle_distance < Field::SURFACE && middle_distance < distance {
+                        blocked = middle;
+                    } else {
+                        allowed = middle;
+                    }
+                }
+
+                return allowed;
+            }
+
+            distance = travel_distance;
+            allowed = travel;
+        }
+
+        return delta;
+    }
+
+    /// Smallest distance from the field to the points of the outline of the collider centered on
+    /// `position`.
+    fn field_distance(&self, position: Vec2, field: &Field) -> f32 {
+        let min = position - self.half_size;
+        let size = self.half_size * 2.0;
+        let samples = (size / Self::FIELD_SAMPLE_SPACING).ceil().as_ivec2();
+
+        let mut distance = f32::INFINITY;
+
+        for x in 0..=samples.x {
+            let x = min.x + size.x * x as f32 / samples.x as f32;
+
+            distance = distance.min(field.distance(Vec2::new(x, min.y))).min(field.distance(Vec2::new(x, min.y + size.y)));
+        }
+
+        for y in 1..samples.y {
+            let y = min.y + size.y * y as f32 / samples.y as f32;
+
+            distance = distance.min(field.distance(Vec2::new(min.x, y))).min(field.distance(Vec2::new(min.x + size.x, y)));
+        }
+
+        return distance;
+    }
+
+    /// Tile holding the lower edge of a range.
+    fn first_tile(min: f32) -> i32 {
+        return ((min + Self::SKIN / 2.0) / World::TILE_SIZE).floor() as i32;
+    }
+
+    /// Tile holding the upper edge of a range.
+    fn last_tile(max: f32) -> i32 {
+        return ((max - Self::SKIN / 2.0) / World::TILE_SIZE).floor() as i32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{
+        IVec2,
+        Vec2,
+    };
+
+    use crate::logic::play::{
+        entity::Transform,
+        sdf::{
+            Field,
+            Operation,
+            Primitive,
+            SdfShape,
+        },
+        world::World,
+    };
+
+    use super::Player;
+
+    /// Level with only its border walls, and walls on `walls`.
+    fn world(walls: &[IVec2]) -> World {
+        let mut world = World::new();
+        world.field.shapes.clear();
+
+        for x in 1..World::WIDTH as i32 - 1 {
+            for y in 1..World::HEIGHT as i32 - 1 {
+                world.set_tile(IVec2::new(x, y), 0);
+            }
+        }
+
+        for wall in walls {
+            world.set_tile(*wall, 1);
+        }
+
+        return world;
+    }
+
+    #[test]
+    fn fast_player_stops_at_a_thin_wall() {
+        let world = world(&[IVec2::new(20, 10)]);
+
+        for speed in [300.0, 3000.0, 30000.0] {
+            let mut player = Player::new();
+            let mut transform = Transform::new(Vec2::new(110.0, 210.0));
+
+            player.velocity = Vec2::new(speed, 0.0);
+            player.move_and_collide(&mut transform, &world, Vec2::new(speed, 0.0));
+
+            let gap = 20.0 * World::TILE_SIZE - (transform.position.x + player.half_size.x);
+
+            assert!(gap > 0.0 && gap <= 2.0 * Player::SKIN, "gap of {} at {}", gap, speed);
+            assert_eq!(player.velocity.x, 0.0);
+            assert!(!player.on_ground);
+        }
+    }
+
+    #[test]
+    fn landing_sets_on_ground() {
+        let world = world(&[IVec2::new(10, 5)]);
+        let mut player = Player::new();
+        let mut transform = Transform::new(Vec2::new(210.0, 160.0));
+
+        // Falls short of the floor, then reaches it
+        player.move_and_collide(&mut transform, &world, Vec2::new(0.0, -20.0));
+
+        assert!(!player.on_ground);
+
+        player.velocity = Vec2::new(0.0, -500.0);
+        player.move_and_collide(&mut transform, &world, Vec2::new(0.0, -50.0));
+
+        let gap = transform.position.y - player.half_size.y - 6.0 * World::TILE_SIZE;
+
+        assert!(player.on_ground);
+        assert!(gap > 0.0 && gap <= 2.0 * Player::SKIN, "gap of {}", gap);
+        assert_eq!(player.velocity.y, 0.0);
+
+        // Bumping a ceiling isn't standing
+        let mut transform = Transform::new(Vec2::new(210.0, 80.0));
+
+        player.move_and_collide(&mut transform, &world, Vec2::new(0.0, 50.0));
+
+        assert!(!player.on_ground);
+    }
+
+    #[test]
+    fn player_stops_at_a_thin_field_segment() {
+        let mut world = world(&[]);
+        world.field.shapes.push(SdfShape::new(Primitive::Segment { a: Vec2::new(300.0, 300.0), b: Vec2::new(500.0, 300.0) }, Operation::Union));
+        world.field.shapes.push(SdfShape::new(Primitive::Segment { a: Vec2::new(600.0, 100.0), b: Vec2::new(600.0, 500.0) }, Operation::Union));
+
+        // Falling onto the horizontal one
+        let mut player = Player::new();
+        let mut transform = Transform::new(Vec2::new(400.0, 450.0));
+
+        player.move_and_collide(&mut transform, &world, Vec2::new(0.0, -300.0));
+
+        let bottom = transform.position.y - player.half_size.y;
+
+        assert!(player.on_ground);
+        assert!(bottom > 300.0 + Field::SURFACE - 0.1 && bottom < 300.0 + Field::SURFACE + 0.2, "bottom at {}", bottom);
+
+        // Running into the vertical one
+        let mut player = Player::new();
+        let mut transform = Transform::new(Vec2::new(500.0, 250.0));
+
+        player.move_and_collide(&mut transform, &world, Vec2::new(400.0, 0.0));
+
+        let right = transform.position.x + player.half_size.x;
+
+        assert!(right > 600.0 - Field::SURFACE - 0.2 && right < 600.0 - Field::SURFACE + 0.1, "right at {}", right);
+        assert_eq!(player.velocity.x, 0.0);
+    }
+}
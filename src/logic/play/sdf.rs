@@ -0,0 +1,220 @@
+use glam::Vec2;
+
+/// Analytic shape, given by its signed distance: negative inside, positive outside.
+#[derive(Clone)]
+pub enum Primitive {
+    Circle { center: Vec2, radius: f32 },
+    /// Segment from `a` to `b` inflated by `radius`.
+    Capsule { a: Vec2, b: Vec2, radius: f32 },
+    /// Box rotated counter-clockwise by `rotation` radians around its center.
+    Box { center: Vec2, half_size: Vec2, rotation: f32 },
+    /// Infinitely thin line, solid within `Field::SURFACE` of it.
+    Segment { a: Vec2, b: Vec2 },
+    /// Polygon inflated by `radius`. Only the first `MAX_POLYGON_VERTICES` reach the GPU.
+    RoundedPolygon { vertices: Vec<Vec2>, radius: f32 },
+}
+
+impl Primitive {
+    pub const MAX_POLYGON_VERTICES: usize = 8;
+
+    /// Identifier of the primitive on the GPU side.
+    pub fn kind(&self) -> u32 {
+        return match self {
+            Primitive::Circle { .. } => 0,
+            Primitive::Capsule { .. } => 1,
+            Primitive::Box { .. } => 2,
+            Primitive::Segment { .. } => 3,
+            Primitive::RoundedPolygon { .. } => 4,
+        };
+    }
+
+    pub fn distance(&self, position: Vec2) -> f32 {
+        return match self {
+            Primitive::Circle { center, radius } => position.distance(*center) - radius,
+            Primitive::Capsule { a, b, radius } => Self::segment_distance(position, *a, *b) - radius,
+            Primitive::Box { center, half_size, rotation } => {
+                let local = Vec2::from_angle(-rotation).rotate(position - *center);
+                let d = local.abs() - *half_size;
+
+                d.max(Vec2::ZERO).length() + d.x.max(d.y).min(0.0)
+            }
+            Primitive::Segment { a, b } => Self::segment_distance(position, *a, *b),
+            Primitive::RoundedPolygon { vertices, radius } => Self::polygon_distance(position, vertices) - radius,
+        };
+    }
+
+    fn segment_distance(position: Vec2, a: Vec2, b: Vec2) -> f32 {
+        let pa = position - a;
+        let ba = b - a;
+        let h = (pa.dot(ba) / ba.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+
+        return (pa - ba * h).length();
+    }
+
+    /// Distance to the closest edge, negative inside by the crossing number of the edges.
+    fn polygon_distance(position: Vec2, vertices: &[Vec2]) -> f32 {
+        let count = vertices.len().min(Self::MAX_POLYGON_VERTICES);
+
+        if count == 0 {
+            return Field::EMPTY;
+        }
+
+        let mut distance = position.distance_squared(vertices[0]);
+        let mut sign = 1.0;
+
+        for i in 0..count {
+            let vertex = vertices[i];
+            let previous = vertices[(i + count - 1) % count];
+
+            let edge = previous - vertex;
+            let w = position - vertex;
+            let b = w - edge * (w.dot(edge) / edge.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+
+            distance = distance.min(b.length_squared());
+
+            let above = position.y >= vertex.y;
+            let below = position.y < previous.y;
+            let left = edge.x * w.y > edge.y * w.x;
+
+            if (above && below && left) || (!above && !below && !left) {
+                sign = -sign;
+            }
+        }
+
+        return sign * distance.sqrt();
+    }
+}
+
+/// How a primitive is merged into the shapes before it.
+#[derive(Clone, Copy)]
+pub enum Operation {
+    Union,
+    /// Carves the primitive out of the shapes before it.
+    Subtraction,
+    /// Union blending the shapes over a distance of `smoothness`.
+    SmoothUnion { smoothness: f32 },
+    SmoothSubtraction { smoothness: f32 },
+}
+
+impl Operation {
+    /// Identifier and smoothness of the operation on the GPU side.
+    pub fn parameters(&self) -> (u32, f32) {
+        return match *self {
+            Operation::Union => (0, 0.0),
+            Operation::Subtraction => (1, 0.0),
+            Operation::SmoothUnion { smoothness } => (2, smoothness),
+            Operation::SmoothSubtraction { smoothness } => (3, smoothness),
+        };
+    }
+
+    fn apply(&self, field: f32, primitive: f32) -> f32 {
+        match *self {
+            Operation::Union => {
+                return field.min(primitive);
+            }
+            Operation::Subtraction => {
+                return field.max(-primitive);
+            }
+            Operation::SmoothUnion { smoothness } => {
+                let k = smoothness.max(f32::EPSILON);
+                let h = (0.5 + 0.5 * (primitive - field) / k).clamp(0.0, 1.0);
+
+                return primitive + (field - primitive) * h - k * h * (1.0 - h);
+            }
+            Operation::SmoothSubtraction { smoothness } => {
+                let k = smoothness.max(f32::EPSILON);
+                let h = (0.5 - 0.5 * (field + primitive) / k).clamp(0.0, 1.0);
+
+                return field + (-primitive - field) * h + k * h * (1.0 - h);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SdfShape {
+    pub primitive: Primitive,
+    pub operation: Operation,
+}
+
+impl SdfShape {
+    pub fn new(primitive: Primitive, operation: Operation) -> Self {
+        return Self {
+            primitive,
+            operation,
+        };
+    }
+}
+
+/// Level geometry made of analytic shapes, merged in order into a single distance field. The
+/// field is solid like a wall where it is below `SURFACE`. Mirrors `field_distance` and
+/// `field_trace` in `lighting.wgsl`.
+#[derive(Clone)]
+pub struct Field {
+    pub shapes: Vec<SdfShape>,
+}
+
+impl Field {
+    pub const MAX_SHAPES: usize = 32;
+
+    /// Distance of an empty field. Finite, so the smooth operations don't turn it into NaN.
+    pub const EMPTY: f32 = 1e30;
+    /// Distance under which a point counts as on the surface.
+    pub const SURFACE: f32 = 0.5;
+    /// Steps and distance after which the sphere tracing gives up.
+    const MAX_STEPS: usize = 64;
+    const MAX_DISTANCE: f32 = 1500.0;
+
+    pub fn new() -> Self {
+        return Self {
+            shapes: Vec::new(),
+        };
+    }
+
+    /// Signed distance to the combined shapes, `EMPTY` when there is none.
+    pub fn distance(&self, position: Vec2) -> f32 {
+        return self.shapes.iter()
+            .take(Self::MAX_SHAPES)
+            .fold(Self::EMPTY, |field, shape| shape.operation.apply(field, shape.primitive.distance(position)));
+    }
+
+    pub fn is_solid(&self, position: Vec2) -> bool {
+        return self.distance(position) < Self::SURFACE;
+    }
+
+    /// Direction in which the distance grows the fastest, pointing out of the shapes.
+    pub fn normal(&self, position: Vec2) -> Vec2 {
+        let epsilon = Self::SURFACE / 2.0;
+
+        let x = self.distance(position + Vec2::X * epsilon) - self.distance(position - Vec2::X * epsilon);
+        let y = self.distance(position + Vec2::Y * epsilon) - self.distance(position - Vec2::Y * epsilon);
+
+        return Vec2::new(x, y).normalize_or_zero();
+    }
+
+    /// Sphere traces the field, returning the distance along the ray to the surface if it reaches
+    /// one.
+    pub fn trace(&self, ray_origin: Vec2, ray_direction: Vec2) -> Option<f32> {
+        if self.shapes.is_empty() {
+            return None;
+        }
+
+        let mut t = 0.0;
+
+        for _ in 0..Self::MAX_STEPS {
+            let distance = self.distance(ray_origin + ray_direction * t);
+
+            if distance < Self::SURFACE {
+                return Some(t);
+            }
+
+            t += distance;
+
+            if t > Self::MAX_DISTANCE {
+                return None;
+            }
+        }
+
+        return None;
+    }
+}
@@ -0,0 +1,51 @@
+use std::f32::consts::PI;
+
+use glam::{
+    Vec2,
+    Vec3,
+};
+
+/// Global lighting of an outdoor level: a sun at infinity that casts hard shadows and an ambient
+/// term for every pixel that can see the top edge of the world. Colors are linear RGB.
+pub struct Sky {
+    pub sun_color: Vec3,
+    pub sun_intensity: f32,
+
+    pub sky_color: Vec3,
+    pub sky_intensity: f32,
+}
+
+impl Sky {
+    pub fn new() -> Self {
+        return Self {
+            sun_color: Vec3::new(1.0, 0.95, 0.8),
+            sun_intensity: 0.6,
+
+            sky_color: Vec3::new(0.4, 0.6, 1.0),
+            sky_intensity: 0.3,
+        };
+    }
+
+    /// Direction in which the sun light travels for a time of day given in hours. The sun rises
+    /// on the left at 6h, stands at the zenith at 12h and sets on the right at 18h.
+    pub fn sun_direction(time_of_day: f32) -> Vec2 {
+        let angle = (time_of_day - 6.0) / 12.0 * PI;
+
+        return -Vec2::new(-angle.cos(), angle.sin());
+    }
+
+    /// Fraction of the daylight reaching the level, 0 at night and 1 at noon.
+    pub fn daylight(time_of_day: f32) -> f32 {
+        let angle = (time_of_day - 6.0) / 12.0 * PI;
+
+        return angle.sin().max(0.0);
+    }
+
+    pub fn sun_radiance(&self, time_of_day: f32) -> Vec3 {
+        return self.sun_color * self.sun_intensity * Self::daylight(time_of_day);
+    }
+
+    pub fn ambient_radiance(&self, time_of_day: f32) -> Vec3 {
+        return self.sky_color * self.sky_intensity * Self::daylight(time_of_day);
+    }
+}
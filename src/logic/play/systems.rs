@@ -0,0 +1,124 @@
+use glam::Vec2;
+
+use crate::input::Input;
+use crate::logic::play::{
+    entity::{
+        Entities,
+        Transform,
+    },
+    npc::{
+        NpcState,
+        Surroundings,
+    },
+    particle::Particle,
+    pathfinding::Pathfinder,
+    world::World,
+};
+
+// Systems of the fixed update, each one a pass over the components it needs. `Play::update` runs
+// them in order once per simulation step.
+
+/// Keeps the state of the last step for interpolation, before anything moves.
+pub fn begin_step(entities: &mut Entities) {
+    for (_, transform) in entities.transforms.iter_mut() {
+        transform.previous_position = transform.position;
+    }
+
+    for (entity, occluder) in entities.occluders.iter() {
+        entities.previous_occluders.insert(entity, occluder.clone());
+    }
+}
+
+/// Moves the occluders following a scripted prop.
+pub fn animate_props(entities: &mut Entities, elapsed: f32) {
+    for (entity, prop) in entities.props.iter() {
+        if let Some(occluder) = entities.occluders.get_mut(entity) {
+            occluder.shape = prop.shape(elapsed);
+        }
+    }
+}
+
+pub fn move_players(entities: &mut Entities, delta_time: f32, world: &World, input: &Input) {
+    for (entity, player) in entities.players.iter_mut() {
+        if let Some(transform) = entities.transforms.get_mut(entity) {
+            player.update(transform, delta_time, world, input);
+        }
+    }
+}
+
+/// Runs the agents, chasing `target`, and shows their state with the color of their body.
+pub fn think_npcs(entities: &mut Entities, delta_time: f32, surroundings: &Surroundings, pathfinder: &mut Pathfinder, target: Vec2) {
+    for (entity, npc) in entities.npcs.iter_mut() {
+        let Some(transform) = entities.transforms.get_mut(entity) else {
+            continue;
+        };
+
+        npc.update(transform, delta_time, surroundings, pathfinder, target);
+
+        if let Some(renderable) = entities.renderables.get_mut(entity) {
+            renderable.color = match npc.state {
+                NpcState::Patrol => [90, 200, 90, 255],
+                NpcState::Alert => [240, 210, 40, 255],
+                NpcState::Chase => [240, 50, 40, 255],
+                NpcState::Search => [240, 130, 30, 255],
+            };
+        }
+    }
+}
+
+/// Spawns the particles owed by the emitters, at their position.
+pub fn emit_particles(entities: &mut Entities, delta_time: f32) {
+    let mut spawned = Vec::new();
+
+    for (entity, emitter) in entities.emitters.iter_mut() {
+        let Some(transform) = entities.transforms.get(entity) else {
+            continue;
+        };
+
+        spawned.extend(emitter.emit(delta_time).into_iter().map(|particle| (transform.position, particle)));
+    }
+
+    for (position, particle) in spawned {
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, Transform::new(position));
+        entities.particles.insert(entity, particle);
+    }
+}
+
+/// Moves and ages the particles, bouncing them off the tiles and despawning the expired ones.
+pub fn update_particles(entities: &mut Entities, delta_time: f32, world: &World) {
+    let mut expired = Vec::new();
+
+    for (entity, particle) in entities.particles.iter_mut() {
+        particle.age += delta_time;
+
+        if particle.is_expired() {
+            expired.push(entity);
+
+            continue;
+        }
+
+        particle.velocity += particle.acceleration * delta_time;
+        particle.size = (particle.size + particle.growth * delta_time).max(0.0);
+
+        let Some(transform) = entities.transforms.get_mut(entity) else {
+            continue;
+        };
+
+        // One axis at a time, so a particle hitting a wall keeps sliding along it
+        for axis in 0..2 {
+            let mut position = transform.position;
+            position[axis] += particle.velocity[axis] * delta_time;
+
+            if world.is_solid((position / World::TILE_SIZE).floor().as_ivec2()) || world.field.is_solid(position) {
+                particle.velocity[axis] *= -Particle::RESTITUTION;
+            } else {
+                transform.position = position;
+            }
+        }
+    }
+
+    for entity in expired {
+        entities.despawn(entity);
+    }
+}
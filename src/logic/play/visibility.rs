@@ -0,0 +1,248 @@
+use std::f32::consts::TAU;
+
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use crate::logic::play::{
+    occluder::{
+        Occluder,
+        Shape,
+    },
+    sdf::{
+        Field,
+        Primitive,
+    },
+    world::World,
+};
+
+/// Area seen from a point, up to a radius, as a star shaped polygon around it. Built by an angle
+/// sweep: rays are cast towards every corner between a solid and an empty tile and along the
+/// tangents to the rounded corners and circles of the occluders and the field, slightly on each
+/// side of them, and at regular angles to follow the arc of the radius and the blended walls.
+/// Tiles, the distance field and the occluders block the view, like in `ray_check`.
+pub struct VisibilityPolygon {
+    pub origin: Vec2,
+    pub radius: f32,
+
+    /// Counter-clockwise around the origin, sorted by `angles`.
+    pub vertices: Vec<Vec2>,
+    angles: Vec<f32>,
+}
+
+impl VisibilityPolygon {
+    /// Rays spread evenly around the origin, on top of the ones aimed at tile corners.
+    const ARC_RAYS: usize = 256;
+    /// Angle in radians between a corner and the rays passing on each side of it.
+    const CORNER_EPSILON: f32 = 1e-4;
+
+    pub fn new(world: &World, occluders: &[Occluder], origin: Vec2, radius: f32) -> Self {
+        let mut angles: Vec<f32> = (0..Self::ARC_RAYS).map(|i| i as f32 / Self::ARC_RAYS as f32 * TAU).collect();
+
+        let tile_corners = Self::corners(world, origin, radius).into_iter().map(|corner| (corner, 0.0));
+
+        for (corner, rounding) in tile_corners.chain(Self::shape_corners(world, occluders)) {
+            let offset = corner - origin;
+
+            // The origin is inside the rounding, it hides nothing
+            if offset.length() <= rounding {
+                continue;
+            }
+
+            let angle = offset.to_angle();
+            let tangent = (rounding / offset.length()).asin();
+
+            angles.push(angle);
+
+            for side in [angle - tangent, angle + tangent] {
+                angles.extend_from_slice(&[side - Self::CORNER_EPSILON, side, side + Self::CORNER_EPSILON]);
+            }
+        }
+
+        for angle in angles.iter_mut() {
+            *angle = angle.rem_euclid(TAU);
+        }
+
+        angles.sort_by(f32::total_cmp);
+        angles.dedup();
+
+        let vertices = angles.iter()
+            .map(|angle| {
+                let direction = Vec2::from_angle(*angle);
+
+                return origin + direction * Self::cast(world, occluders, origin, direction, radius);
+            })
+            .collect();
+
+        return Self {
+            origin,
+            radius,
+
+            vertices,
+            angles,
+        };
+    }
+
+    /// Tile corners within the radius where solid and empty tiles meet, the only places where the
+    /// outline of the view can turn.
+    fn corners(world: &World, origin: Vec2, radius: f32) -> Vec<Vec2> {
+        let min = ((origin - radius) / World::TILE_SIZE).floor().as_ivec2();
+        let max = ((origin + radius) / World::TILE_SIZE).ceil().as_ivec2();
+
+        let mut corners = Vec::new();
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let around = [
+                    world.is_solid(IVec2::new(x - 1, y - 1)),
+                    world.is_solid(IVec2::new(x, y - 1)),
+                    world.is_solid(IVec2::new(x - 1, y)),
+                    world.is_solid(IVec2::new(x, y)),
+                ];
+
+                if around.iter().all(|solid| *solid) || around.iter().all(|solid| !*solid) {
+                    continue;
+                }
+
+                let corner = IVec2::new(x, y).as_vec2() * World::TILE_SIZE;
+
+                if corner.distance(origin) <= radius {
+                    corners.push(corner);
+                }
+            }
+        }
+
+        return corners;
+    }
+
+    /// Corners of the occluders and of the field, with the radius they are rounded by. Rays stop
+    /// `Field::SURFACE` before the field, which rounds its sharp corners too.
+    fn shape_corners(world: &World, occluders: &[Occluder]) -> Vec<(Vec2, f32)> {
+        let mut corners = Vec::new();
+
+        for occluder in occluders {
+            match &occluder.shape {
+                Shape::Circle { center, radius } => corners.push((*center, *radius)),
+                Shape::Aabb { min, max } => corners.extend([*min, Vec2::new(max.x, min.y), *max, Vec2::new(min.x, max.y)].map(|corner| (corner, 0.0))),
+                Shape::Polygon(vertices) => corners.extend(vertices.iter().map(|vertex| (*vertex, 0.0))),
+            }
+        }
+
+        for shape in &world.field.shapes {
+            match &shape.primitive {
+                Primitive::Circle { center, radius } => corners.push((*center, radius + Field::SURFACE)),
+                Primitive::Capsule { a, b, radius } => corners.extend_from_slice(&[(*a, radius + Field::SURFACE), (*b, radius + Field::SURFACE)]),
+                Primitive::Box { center, half_size, rotation } => {
+                    let rotation = Vec2::from_angle(*rotation);
+
+                    for corner in [Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0)] {
+                        corners.push((*center + rotation.rotate(corner * *half_size), Field::SURFACE));
+                    }
+                }
+                Primitive::Segment { a, b } => corners.extend_from_slice(&[(*a, Field::SURFACE), (*b, Field::SURFACE)]),
+                Primitive::RoundedPolygon { vertices, radius } => corners.extend(vertices.iter().map(|vertex| (*vertex, radius + Field::SURFACE))),
+            }
+        }
+
+        return corners;
+    }
+
+    /// Distance along the ray to the first tile, field surface or occluder, at most `max_distance`.
+    pub fn cast(world: &World, occluders: &[Occluder], origin: Vec2, direction: Vec2, max_distance: f32) -> f32 {
+        return world.raycast_with_occluders(occluders, origin, direction, max_distance).map_or(max_distance, |hit| hit.distance);
+    }
+
+    /// Whether the point can be seen from the origin.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let offset = point - self.origin;
+
+        if offset.length() > self.radius || self.vertices.len() < 2 {
+            return false;
+        }
+
+        let angle = offset.to_angle().rem_euclid(TAU);
+
+        // Edge of the polygon spanning the angle of the point, wrapping around after the last one
+        let next = self.angles.partition_point(|vertex_angle| *vertex_angle <= angle) % self.angles.len();
+        let previous = (next + self.angles.len() - 1) % self.angles.len();
+
+        let a = self.vertices[previous];
+        let b = self.vertices[next];
+
+        return (b - a).perp_dot(point - a) >= 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{
+        Vec2,
+        Vec3,
+    };
+
+    use crate::logic::play::{
+        lighting,
+        occluder::{
+            Occluder,
+            Shape,
+        },
+        world::World,
+    };
+
+    use super::VisibilityPolygon;
+
+    const RADIUS: f32 = 250.0;
+    /// Spacing of the sampled points, each standing for a square of this side.
+    const STEP: f32 = 2.0;
+    /// Disagreement allowed per view, a few samples along the edges: rays grazing the field are
+    /// decided by sphere tracing within a fraction of a unit, and the curves between the tangent
+    /// rays are followed by straight edges.
+    const MAX_DISAGREEMENT: f32 = 64.0;
+
+    fn crates() -> Vec<Occluder> {
+        return vec![
+            Occluder::new(Shape::Aabb { min: Vec2::new(300.0, 200.0), max: Vec2::new(340.0, 240.0) }, Vec3::ONE),
+            Occluder::new(Shape::Circle { center: Vec2::new(640.0, 430.0), radius: 15.0 }, Vec3::ONE),
+            Occluder::new(Shape::Polygon(vec![Vec2::new(450.0, 100.0), Vec2::new(480.0, 100.0), Vec2::new(465.0, 130.0)]), Vec3::ONE),
+        ];
+    }
+
+    /// Area in which the polygon and `ray_check` disagree, estimated on a grid of points covering
+    /// the radius, field and occluders included.
+    fn disagreement(world: &World, occluders: &[Occluder], origin: Vec2) -> f32 {
+        let view = VisibilityPolygon::new(world, occluders, origin, RADIUS);
+        let steps = (RADIUS / STEP) as i32;
+
+        let mut area = 0.0;
+
+        for x in -steps..=steps {
+            for y in -steps..=steps {
+                // Off the tile edges, which belong to either side
+                let point = origin + (Vec2::new(x as f32, y as f32) + 0.37) * STEP;
+
+                if point.distance(origin) < RADIUS - 1.0 && view.contains(point) != lighting::ray_check(world, occluders, origin, point) {
+                    area += STEP * STEP;
+                }
+            }
+        }
+
+        return area;
+    }
+
+    #[test]
+    fn contains_matches_ray_check() {
+        let world = World::new();
+
+        // Next to the box, the rocks, the wire, the rounded polygon and in the open
+        let origins = [Vec2::new(200.0, 300.0), Vec2::new(320.0, 170.0), Vec2::new(605.0, 463.0), Vec2::new(830.0, 250.0), Vec2::new(440.0, 115.0)];
+
+        for occluders in [Vec::new(), crates()] {
+            for origin in origins {
+                let area = disagreement(&world, &occluders, origin);
+
+                assert!(area <= MAX_DISAGREEMENT, "{} units disagree from {} with {} occluders", area, origin, occluders.len());
+            }
+        }
+    }
+}
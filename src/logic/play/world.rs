@@ -1,8 +1,55 @@
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use crate::logic::play::occluder::Occluder;
+use crate::logic::play::sdf::{
+    Field,
+    Operation,
+    Primitive,
+    SdfShape,
+};
+use crate::logic::play::sky::Sky;
+
+/// First wall met by a ray cast through the world.
+#[derive(Clone, Copy)]
+pub struct Hit {
+    /// Where the ray meets the wall, on its surface.
+    pub position: Vec2,
+    /// Unit vector pointing out of the wall.
+    pub normal: Vec2,
+    /// Solid tile that was hit, `None` for the distance field and the occluders.
+    pub tile: Option<IVec2>,
+    /// Distance along the ray from its origin to `position`.
+    pub distance: f32,
+}
+
 pub struct World {
-    pub tiles: [u32; 30 * 50],
+    /// Column major grid, 0 is empty, 1 is a wall and 2 a glowing wall. Changed through
+    /// `set_tile`, so the caches built on the tiles notice it.
+    pub tiles: [u32; World::HEIGHT * World::WIDTH],
+    /// Incremented on every change of the tiles.
+    revision: u64,
+
+    /// Curved and slanted walls. The player and the particles collide with it like with the
+    /// tiles, the paths of the NPCs only go around the tiles.
+    pub field: Field,
+
+    /// Column major like the tiles, whether each tile has ever been seen by the player. Kept for
+    /// the whole level, the fog of war shows these tiles as a memory.
+    pub explored: [bool; World::HEIGHT * World::WIDTH],
+
+    /// Sun and sky lighting, only set for outdoor levels.
+    pub sky: Option<Sky>,
 }
 
 impl World {
+    pub const WIDTH: usize = 50;
+    pub const HEIGHT: usize = 30;
+
+    pub const TILE_SIZE: f32 = 20.0;
+
     pub fn new() -> Self {
         let mut tiles = [0; 30 * 50];
 
@@ -18,18 +65,317 @@ impl World {
                     tiles[x * 30 + y] = 0;
                 }
 
-                tiles[0 * 30 + y] = 1;
+                tiles[y] = 1;
                 tiles[49 * 30 + y] = 1;
             }
 
-            tiles[x * 30 + 0] = 1;
+            tiles[x * 30] = 1;
             tiles[x * 30 + 29] = 1;
         }
 
-        tiles[28 * 30 + 4] = 1;
+        tiles[28 * 30 + 4] = 2;
+
+        let mut field = Field::new();
+        field.shapes = vec![
+            // Two blended rocks
+            SdfShape::new(Primitive::Circle { center: Vec2::new(500.0, 500.0), radius: 25.0 }, Operation::Union),
+            SdfShape::new(Primitive::Circle { center: Vec2::new(540.0, 485.0), radius: 18.0 }, Operation::SmoothUnion { smoothness: 15.0 }),
+            // Slanted pipe
+            SdfShape::new(Primitive::Capsule { a: Vec2::new(780.0, 380.0), b: Vec2::new(830.0, 440.0), radius: 8.0 }, Operation::Union),
+            // Tilted block with a hole
+            SdfShape::new(Primitive::Box { center: Vec2::new(170.0, 380.0), half_size: Vec2::new(30.0, 15.0), rotation: 0.4 }, Operation::Union),
+            SdfShape::new(Primitive::Circle { center: Vec2::new(170.0, 380.0), radius: 9.0 }, Operation::Subtraction),
+            // Wire
+            SdfShape::new(Primitive::Segment { a: Vec2::new(620.0, 520.0), b: Vec2::new(690.0, 470.0) }, Operation::Union),
+            // Rounded ramp
+            SdfShape::new(Primitive::RoundedPolygon { vertices: vec![Vec2::new(370.0, 100.0), Vec2::new(430.0, 100.0), Vec2::new(430.0, 140.0)], radius: 4.0 }, Operation::Union),
+        ];
 
         return Self {
             tiles,
+            revision: 0,
+
+            field,
+
+            explored: [false; 30 * 50],
+
+            sky: None,
+        };
+    }
+
+    /// The level of `new` opened to the sky: the top border is removed above the cave, letting the
+    /// sun and the sky light in.
+    pub fn outdoor() -> Self {
+        let mut world = Self::new();
+        let y = Self::HEIGHT - 1;
+
+        for x in 1..Self::WIDTH - 1 {
+            let dx = x as f32 - 25.0;
+            let dy = y as f32 - 15.0;
+
+            if (dx * dx + dy * dy).sqrt() <= 20.0 {
+                world.tiles[x * Self::HEIGHT + y] = 0;
+            }
+        }
+
+        world.sky = Some(Sky::new());
+
+        return world;
+    }
+
+    /// Size of the tile grid in world units.
+    pub fn size() -> Vec2 {
+        return Vec2::new(Self::WIDTH as f32 * Self::TILE_SIZE, Self::HEIGHT as f32 * Self::TILE_SIZE);
+    }
+
+    /// Whether a position in world space lies inside the tile grid.
+    pub fn contains(position: Vec2) -> bool {
+        return position.x >= 0.0 && position.x < Self::WIDTH as f32 * Self::TILE_SIZE && position.y >= 0.0 && position.y < Self::HEIGHT as f32 * Self::TILE_SIZE;
+    }
+
+    pub fn tile_at(position: Vec2) -> IVec2 {
+        return (position / Self::TILE_SIZE).floor().as_ivec2();
+    }
+
+    /// Position of a tile in the column major arrays, `None` outside the grid.
+    pub fn index(tile: IVec2) -> Option<usize> {
+        if tile.x < 0 || tile.x >= Self::WIDTH as i32 || tile.y < 0 || tile.y >= Self::HEIGHT as i32 {
+            return None;
+        }
+
+        return Some(tile.x as usize * Self::HEIGHT + tile.y as usize);
+    }
+
+    /// Whether a tile blocks light, tiles outside the grid are considered solid.
+    pub fn is_solid(&self, tile: IVec2) -> bool {
+        return Self::index(tile).is_none_or(|index| self.tiles[index] != 0);
+    }
+
+    /// Counter identifying the current state of the tiles, it changes whenever a tile does.
+    pub fn revision(&self) -> u64 {
+        return self.revision;
+    }
+
+    /// Replaces a tile, tiles outside the grid are ignored.
+    pub fn set_tile(&mut self, tile: IVec2, value: u32) {
+        let Some(index) = Self::index(tile) else {
+            return;
+        };
+
+        if self.tiles[index] != value {
+            self.tiles[index] = value;
+            self.revision += 1;
+        }
+    }
+
+    pub fn is_explored(&self, tile: IVec2) -> bool {
+        return Self::index(tile).is_some_and(|index| self.explored[index]);
+    }
+
+    /// Marks a tile as seen, tiles outside the grid are ignored.
+    pub fn explore(&mut self, tile: IVec2) {
+        if let Some(index) = Self::index(tile) {
+            self.explored[index] = true;
+        }
+    }
+
+    /// First tile or field surface the ray meets within `max_distance`, through the occluders.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        return self.raycast_with_occluders(&[], origin, direction, max_distance);
+    }
+
+    /// First tile, field surface or occluder the ray meets within `max_distance`. The same
+    /// traversal as `ray_hit` and `ray_check` in `lighting.wgsl`: the ray walks the tiles from edge
+    /// to edge, the field is sphere traced and the occluders are intersected analytically. Tiles
+    /// outside the grid are solid, and a ray starting inside a wall hits it right away, facing
+    /// back.
+    pub fn raycast_with_occluders(&self, occluders: &[Occluder], origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        let direction = direction.normalize_or_zero();
+
+        if direction == Vec2::ZERO {
+            return None;
+        }
+
+        let mut tile = (origin / Self::TILE_SIZE).floor().as_ivec2();
+
+        if self.is_solid(tile) {
+            return Some(Hit {
+                position: origin,
+                normal: -direction,
+                tile: Some(tile),
+                distance: 0.0,
+            });
+        }
+
+        let occluder = occluders.iter()
+            .filter_map(|occluder| occluder.shape.entry(origin, direction).map(|distance| (occluder, distance)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let occluder_distance = occluder.map_or(f32::INFINITY, |(_, distance)| distance);
+        let field_distance = self.field.trace(origin, direction).unwrap_or(f32::INFINITY);
+
+        let step = IVec2::new(direction.x.signum() as i32, direction.y.signum() as i32);
+
+        // Distance along the ray to the next vertical and horizontal tile edges, and between them
+        let mut next = Vec2::splat(f32::INFINITY);
+        let mut delta = Vec2::splat(f32::INFINITY);
+
+        for axis in 0..2 {
+            if direction[axis] != 0.0 {
+                let edge = (tile[axis] + (step[axis] > 0) as i32) as f32 * Self::TILE_SIZE;
+
+                next[axis] = (edge - origin[axis]) / direction[axis];
+                delta[axis] = Self::TILE_SIZE / direction[axis].abs();
+            }
+        }
+
+        let obstacle_distance = occluder_distance.min(field_distance);
+        let end = obstacle_distance.min(max_distance);
+
+        loop {
+            let axis = if next.x < next.y { 0 } else { 1 };
+            let distance = next[axis];
+
+            if distance > end {
+                break;
+            }
+
+            tile[axis] += step[axis];
+            next[axis] += delta[axis];
+
+            if self.is_solid(tile) {
+                let mut normal = Vec2::ZERO;
+                normal[axis] = -step[axis] as f32;
+
+                return Some(Hit {
+                    position: origin + direction * distance,
+                    normal,
+                    tile: Some(tile),
+                    distance,
+                });
+            }
+        }
+
+        if obstacle_distance > max_distance {
+            return None;
+        }
+
+        let position = origin + direction * obstacle_distance;
+
+        let normal = match occluder {
+            Some((occluder, distance)) if distance <= field_distance => occluder.shape.normal(position),
+            _ => self.field.normal(position),
         };
+
+        return Some(Hit {
+            position,
+            normal,
+            tile: None,
+            distance: obstacle_distance,
+        });
+    }
+
+    /// Whether no tile or field surface lies between the two points, ignoring the occluders.
+    pub fn line_of_sight(&self, a: Vec2, b: Vec2) -> bool {
+        return self.line_of_sight_with_occluders(&[], a, b);
+    }
+
+    /// Whether no tile, field surface or occluder lies between the two points, like `ray_check` in
+    /// `lighting.wgsl`.
+    pub fn line_of_sight_with_occluders(&self, occluders: &[Occluder], a: Vec2, b: Vec2) -> bool {
+        if a == b {
+            return !self.is_solid((a / Self::TILE_SIZE).floor().as_ivec2()) && !self.field.is_solid(a);
+        }
+
+        return self.raycast_with_occluders(occluders, a, b - a, a.distance(b)).is_none();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{
+        IVec2,
+        Vec2,
+        Vec3,
+    };
+
+    use crate::logic::play::{
+        lighting,
+        occluder::{
+            Occluder,
+            Shape,
+        },
+    };
+
+    use super::World;
+
+    fn crates() -> Vec<Occluder> {
+        return vec![
+            Occluder::new(Shape::Aabb { min: Vec2::new(300.0, 200.0), max: Vec2::new(340.0, 240.0) }, Vec3::ONE),
+            Occluder::new(Shape::Circle { center: Vec2::new(700.0, 150.0), radius: 15.0 }, Vec3::ONE),
+            Occluder::new(Shape::Polygon(vec![Vec2::new(450.0, 100.0), Vec2::new(480.0, 100.0), Vec2::new(465.0, 130.0)]), Vec3::ONE),
+        ];
+    }
+
+    /// Points on a grid offset from the tile edges, plus some on the edges and corners.
+    fn points() -> Vec<Vec2> {
+        let mut points = Vec::new();
+
+        for x in (7..1000).step_by(37) {
+            for y in (3..600).step_by(29) {
+                points.push(Vec2::new(x as f32, y as f32));
+            }
+        }
+
+        points.extend_from_slice(&[Vec2::new(20.0, 20.0), Vec2::new(40.0, 580.0), Vec2::new(980.0, 300.0), Vec2::new(500.0, 0.0)]);
+
+        return points;
+    }
+
+    #[test]
+    fn line_of_sight_matches_ray_check() {
+        for world in [World::new(), World::outdoor()] {
+            for occluders in [Vec::new(), crates()] {
+                let origins = [Vec2::new(200.0, 300.0), Vec2::new(500.0, 300.0), Vec2::new(320.0, 170.0), Vec2::new(650.0, 495.0), Vec2::new(465.0, 90.0)];
+
+                for origin in origins {
+                    for point in points() {
+                        assert_eq!(
+                            world.line_of_sight_with_occluders(&occluders, origin, point),
+                            lighting::ray_check(&world, &occluders, origin, point),
+                            "from {} to {}", origin, point,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn occluders_block_the_line_of_sight() {
+        let world = World::new();
+        let occluders = crates();
+
+        for (a, b) in [(Vec2::new(320.0, 150.0), Vec2::new(320.0, 290.0)), (Vec2::new(650.0, 150.0), Vec2::new(750.0, 150.0)), (Vec2::new(440.0, 115.0), Vec2::new(510.0, 115.0))] {
+            assert!(world.line_of_sight(a, b));
+            assert!(!world.line_of_sight_with_occluders(&occluders, a, b));
+        }
+    }
+
+    #[test]
+    fn walls_and_field_block_the_line_of_sight() {
+        let world = World::new();
+
+        assert!(world.line_of_sight(Vec2::new(200.0, 300.0), Vec2::new(250.0, 450.0)));
+        assert!(!world.line_of_sight(Vec2::new(250.0, 310.0), Vec2::new(770.0, 310.0)));
+        assert!(!world.line_of_sight(Vec2::new(200.0, 300.0), Vec2::new(-100.0, 300.0)));
+        assert!(!world.line_of_sight(Vec2::new(440.0, 500.0), Vec2::new(560.0, 500.0)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn tile_at_rounds_down() {
+        assert_eq!(World::tile_at(Vec2::new(25.0, 39.9)), IVec2::new(1, 1));
+        assert_eq!(World::tile_at(Vec2::new(-0.5, 5.0)), IVec2::new(-1, 0));
+        assert_eq!(World::tile_at(Vec2::new(-20.0, -20.5)), IVec2::new(-1, -2));
+    }
+}
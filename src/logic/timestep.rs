@@ -0,0 +1,42 @@
+/// Turns variable frame times into a whole number of fixed simulation steps, keeping the rest for
+/// the next frames.
+pub struct FixedTimestep {
+    /// Duration of a step in seconds.
+    pub step: f32,
+    /// Steps run at most per frame, the time beyond is dropped so a slow frame doesn't snowball.
+    pub max_steps: u32,
+
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(rate: u32) -> Self {
+        return Self {
+            step: 1.0 / rate as f32,
+            max_steps: 8,
+
+            accumulator: 0.0,
+        };
+    }
+
+    /// Adds the frame time and returns the number of steps to run.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let steps = (self.accumulator / self.step).floor() as u32;
+        self.accumulator -= steps as f32 * self.step;
+
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+
+            return self.max_steps;
+        }
+
+        return steps;
+    }
+
+    /// How far the time is between the last step and the next one, in `[0, 1)`.
+    pub fn alpha(&self) -> f32 {
+        return (self.accumulator / self.step).clamp(0.0, 1.0);
+    }
+}
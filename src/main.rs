@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use winit::{
     event::{
         Event,
@@ -21,13 +23,21 @@ use wgpu::{
     Queue,
     Surface,
     SurfaceConfiguration,
-    TextureFormat,
 };
 
+use crate::input::{
+    action::ActionMap,
+    Input,
+};
 use crate::logic::Logic;
-use crate::renderer::Renderer;
+use crate::profiler::Profiler;
+use crate::renderer::{
+    settings::GraphicsSettings,
+    Renderer,
+};
+use crate::ui::Ui;
 
-async fn build_backend(window: &Window) -> (Instance, Surface, SurfaceConfiguration, Adapter, Device, Queue) {
+async fn build_backend(window: &Window) -> (Instance, Surface<'_>, SurfaceConfiguration, Adapter, Device, Queue) {
     let instance = wgpu::Instance::default();
 
     let surface = instance.create_surface(window).unwrap();
@@ -40,7 +50,8 @@ async fn build_backend(window: &Window) -> (Instance, Surface, SurfaceConfigurat
     let (device, queue) = adapter.request_device(
         &wgpu::DeviceDescriptor {
             label: None,
-            required_features: wgpu::Features::empty(),
+            // Optional, the passes are only timed on the GPU when the adapter has it
+            required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
             required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
         },
         None,
@@ -52,7 +63,13 @@ async fn build_backend(window: &Window) -> (Instance, Surface, SurfaceConfigurat
 
     let mut config = surface.get_default_config(&adapter, size.width, size.height).unwrap();
 
-    config.format = TextureFormat::Bgra8Unorm;
+    // The renderer works in linear space: prefer a surface that encodes to sRGB on write, the tone
+    // mapping pass encodes explicitly otherwise.
+    let capabilities = surface.get_capabilities(&adapter);
+
+    if let Some(format) = capabilities.formats.iter().find(|format| format.is_srgb()) {
+        config.format = *format;
+    }
 
     surface.configure(&device, &config);
 
@@ -68,8 +85,8 @@ pub struct WGPUBackend<'a> {
     queue: Queue,
 }
 
-fn build_wgpu_backed(window: &Window) -> WGPUBackend {
-    let (instance, surface, config, adapter, device, queue) = pollster::block_on(build_backend(&window));
+fn build_wgpu_backed(window: &Window) -> WGPUBackend<'_> {
+    let (instance, surface, config, adapter, device, queue) = pollster::block_on(build_backend(window));
 
     return WGPUBackend {
         instance,
@@ -81,8 +98,13 @@ fn build_wgpu_backed(window: &Window) -> WGPUBackend {
     };
 }
 
+pub mod config;
+pub mod font;
+pub mod input;
 pub mod logic;
+pub mod profiler;
 pub mod renderer;
+pub mod ui;
 
 fn main() {
     let event_loop = EventLoop::new().unwrap();
@@ -101,8 +123,28 @@ fn main() {
 
     let mut backend = build_wgpu_backed(&window);
 
+    let actions = ActionMap::load("config/input.cfg").unwrap_or_else(|error| {
+        eprintln!("Failed to load the input bindings, using the default ones: {}", error);
+
+        return ActionMap::builtin();
+    });
+
+    let settings = GraphicsSettings::load("config/graphics.cfg").unwrap_or_else(|error| {
+        eprintln!("Failed to load the graphics settings, using the default ones: {}", error);
+
+        return GraphicsSettings::builtin();
+    });
+
+    let mut input = Input::new(actions);
     let mut logic = Logic::new();
     let mut renderer = Renderer::new(&backend, &logic);
+    renderer.set_tone_mapping(settings.tone_mapping);
+    renderer.set_lighting_resolution(&backend, settings.lighting);
+    renderer.set_scaling(&backend, &logic, settings.scaling);
+    let mut ui = Ui::new();
+    let mut profiler = Profiler::new(renderer.gpu_sections());
+
+    let mut last_frame = Instant::now();
 
     let window = &window;
     event_loop.run(move |event, target| {
@@ -112,10 +154,15 @@ fn main() {
             Event::AboutToWait => {
                 window.request_redraw();
             },
+            Event::LoopExiting => {
+                println!("Frame times:\n{}", profiler.summary());
+            },
             Event::WindowEvent {
                 event,
                 ..
             } => {
+                input.process_event(&event);
+
                 match event {
                     WindowEvent::Resized(new_size) => {
                         backend.config.width = new_size.width.max(1);
@@ -126,14 +173,25 @@ fn main() {
                     }
                     WindowEvent::CloseRequested => target.exit(),
                     WindowEvent::RedrawRequested => {
-                        renderer.update(&backend, &logic);
+                        let now = Instant::now();
+                        let frame_time = now.duration_since(last_frame).as_secs_f32();
+                        last_frame = now;
+
+                        if let Some(times) = renderer.read_gpu_times(&backend) {
+                            profiler.record_gpu(&times);
+                        }
+
+                        ui.begin(&input, (backend.config.width, backend.config.height));
+                        logic.process_ui(&mut ui, &profiler);
+                        ui.end(&mut input);
+
+                        logic.process_cursor(input.cursor_position, renderer.view_to_world(&backend, &logic, input.cursor_position));
+                        logic.advance(frame_time, &mut input);
+
+                        renderer.update(&backend, &logic, &ui);
                         renderer.render(&backend, &logic);
-                    }
-                    WindowEvent::CursorMoved {
-                        position,
-                        ..
-                    } => {
-                        logic.process_mouse_position((position.x as u32, position.y as u32));
+
+                        profiler.record(frame_time, now.elapsed().as_secs_f32() - renderer.surface_wait());
                     }
                     _ => {}
                 }
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+/// Rolling history of a duration in milliseconds, with statistics over the whole session.
+pub struct History {
+    samples: VecDeque<f32>,
+
+    count: u64,
+    sum: f64,
+    min: f32,
+    max: f32,
+}
+
+impl History {
+    /// Samples kept in the rolling history.
+    pub const LENGTH: usize = 240;
+
+    pub fn new() -> Self {
+        return Self {
+            samples: VecDeque::with_capacity(Self::LENGTH),
+
+            count: 0,
+            sum: 0.0,
+            min: f32::INFINITY,
+            max: 0.0,
+        };
+    }
+
+    pub fn push(&mut self, milliseconds: f32) {
+        if self.samples.len() == Self::LENGTH {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(milliseconds);
+
+        self.count += 1;
+        self.sum += milliseconds as f64;
+        self.min = self.min.min(milliseconds);
+        self.max = self.max.max(milliseconds);
+    }
+
+    /// Rolling history, oldest first.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        return self.samples.iter().copied();
+    }
+
+    pub fn recent_average(&self) -> f32 {
+        return self.samples.iter().sum::<f32>() / self.samples.len().max(1) as f32;
+    }
+
+    pub fn recent_max(&self) -> f32 {
+        return self.samples.iter().copied().fold(0.0, f32::max);
+    }
+
+    /// Mean, minimum and maximum over the session.
+    fn summary(&self, name: &str) -> String {
+        if self.count == 0 {
+            return format!("{:>10}: no samples", name);
+        }
+
+        return format!("{:>10}: mean {:.3} ms, min {:.3} ms, max {:.3} ms over {} frames", name, self.sum / self.count as f64, self.min, self.max, self.count);
+    }
+}
+
+/// Frame times measured on the CPU and, when the device supports timestamp queries, the time the
+/// GPU spends on each section of the frame.
+pub struct Profiler {
+    /// Time between the starts of consecutive frames.
+    pub frame: History,
+    /// Time the CPU spends on a frame, without waiting for the swap chain.
+    pub cpu: History,
+    /// One history per section timed on the GPU, empty without timestamp queries.
+    pub gpu: Vec<(&'static str, History)>,
+}
+
+impl Profiler {
+    /// `gpu_sections` are the sections the renderer times, `None` when it can't.
+    pub fn new(gpu_sections: Option<&[&'static str]>) -> Self {
+        return Self {
+            frame: History::new(),
+            cpu: History::new(),
+            gpu: gpu_sections.unwrap_or(&[]).iter().map(|section| (*section, History::new())).collect(),
+        };
+    }
+
+    /// Records a frame, durations in seconds.
+    pub fn record(&mut self, frame_time: f32, cpu_time: f32) {
+        self.frame.push(frame_time * 1000.0);
+        self.cpu.push(cpu_time * 1000.0);
+    }
+
+    /// Records the seconds the GPU spent on each section, in the order given to `new`.
+    pub fn record_gpu(&mut self, times: &[f32]) {
+        for ((_, history), time) in self.gpu.iter_mut().zip(times) {
+            history.push(time * 1000.0);
+        }
+    }
+
+    /// Statistics of the session, one line per history.
+    pub fn summary(&self) -> String {
+        let mut lines = vec![self.frame.summary("frame"), self.cpu.summary("cpu")];
+
+        if self.gpu.is_empty() {
+            lines.push(format!("{:>10}: timestamp queries unsupported", "gpu"));
+        }
+
+        lines.extend(self.gpu.iter().map(|(section, history)| history.summary(&format!("gpu {}", section))));
+
+        return lines.join("\n");
+    }
+}
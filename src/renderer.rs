@@ -1,3 +1,7 @@
+use std::time::Instant;
+
+use glam::Vec2;
+
 use wgpu::{
     LoadOp,
     Operations,
@@ -7,66 +11,329 @@ use wgpu::{
 
 use crate::{
     logic::Logic,
+    ui::Ui,
     WGPUBackend
 };
 
-use crate::renderer::play::PlayRenderer;
+use crate::renderer::{
+    overlay::OverlayBatch,
+    play::{
+        light_buffer::LightingResolution,
+        PlayRenderer,
+    },
+    post::{
+        bloom::{
+            Bloom,
+            BloomSettings,
+        },
+        fog_of_war::FogOfWar,
+        PostProcessing,
+    },
+    scaling::Scaling,
+    target::RenderTarget,
+    timer::GpuTimer,
+    tone_mapping::{
+        ToneMapping,
+        ToneMappingRenderer,
+    },
+};
 
+pub mod debug;
+pub mod font;
+pub mod overlay;
 pub mod pipeline;
+pub mod post;
+pub mod scaling;
+pub mod settings;
+pub mod screen_quad;
+pub mod sprite;
+pub mod target;
+pub mod timer;
+pub mod tone_mapping;
+pub mod ui;
 
 pub mod play;
 
 pub struct Renderer {
-    play: PlayRenderer
+    play: PlayRenderer,
+
+    scaling: Scaling,
+    hdr_target: RenderTarget,
+
+    post_processing: PostProcessing,
+    fog_of_war: FogOfWar,
+    bloom: Bloom,
+
+    tone_mapping: ToneMapping,
+    tone_mapping_renderer: ToneMappingRenderer,
+
+    overlay: OverlayBatch,
+
+    /// GPU time of the passes, `None` without timestamp queries.
+    timer: Option<GpuTimer>,
+    /// Seconds the last frame waited for the swap chain.
+    surface_wait: f32,
 }
 
 impl Renderer {
+    const STAGES: usize = 2;
+
     pub fn new(wgpu_backend: &WGPUBackend, logic: &Logic) -> Self {
+        let scaling = Scaling::new();
+        let size = scaling.scene_size((wgpu_backend.config.width, wgpu_backend.config.height));
+
+        let hdr_target = RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT);
+
+        let post_processing = PostProcessing::new(wgpu_backend, size);
+        let fog_of_war = FogOfWar::new(wgpu_backend, &logic.play, size);
+        let bloom = Bloom::new(wgpu_backend, size, BloomSettings::new());
+
+        let tone_mapping = ToneMapping::Aces;
+        let tone_mapping_renderer = ToneMappingRenderer::new(wgpu_backend, post_processing.output(&hdr_target.view, Self::STAGES), tone_mapping);
+
         return Self {
-            play: PlayRenderer::new(wgpu_backend, &logic.play)
+            play: PlayRenderer::new(wgpu_backend, &logic.play, size),
+
+            scaling,
+            hdr_target,
+
+            post_processing,
+            fog_of_war,
+            bloom,
+
+            tone_mapping,
+            tone_mapping_renderer,
+
+            overlay: OverlayBatch::new(wgpu_backend),
+
+            timer: GpuTimer::new(wgpu_backend),
+            surface_wait: 0.0,
         };
     }
 
-    pub fn update(&mut self, wgpu_backend: &WGPUBackend, logic: &Logic) {
-        self.play.update(wgpu_backend, &logic.play);
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    pub fn set_lighting_resolution(&mut self, wgpu_backend: &WGPUBackend, resolution: LightingResolution) {
+        self.play.set_lighting_resolution(wgpu_backend, resolution, self.scene_size(wgpu_backend));
+    }
+
+    pub fn set_scaling(&mut self, wgpu_backend: &WGPUBackend, logic: &Logic, scaling: Scaling) {
+        self.scaling = scaling;
+        self.process_resize(wgpu_backend, logic);
+    }
+
+    /// Size of the image the scene is rendered at, in pixels.
+    fn scene_size(&self, wgpu_backend: &WGPUBackend) -> (u32, u32) {
+        return self.scaling.scene_size((wgpu_backend.config.width, wgpu_backend.config.height));
+    }
+
+    pub fn set_bloom(&mut self, settings: BloomSettings) {
+        self.bloom.settings = settings;
+    }
+
+    /// Post-processing stages in the order they are applied to the HDR image.
+    /// Converts a position in window pixels to world space, through the scaling and the camera.
+    pub fn view_to_world(&self, wgpu_backend: &WGPUBackend, logic: &Logic, position: Vec2) -> Vec2 {
+        let size = self.scene_size(wgpu_backend);
+        let viewport = self.scaling.viewport((wgpu_backend.config.width, wgpu_backend.config.height));
+
+        return logic.play.view_camera().view_to_world(viewport.to_scene(position, size), size);
+    }
+
+    /// Sections of the frame timed on the GPU, `None` when the device can't time them.
+    pub fn gpu_sections(&self) -> Option<&'static [&'static str]> {
+        return self.timer.as_ref().map(|_| GpuTimer::SECTIONS.as_slice());
+    }
+
+    /// Seconds the GPU spent on each of the `gpu_sections` in the last timed frame, when new ones
+    /// arrived since the last call.
+    pub fn read_gpu_times(&mut self, wgpu_backend: &WGPUBackend) -> Option<[f32; GpuTimer::SECTIONS.len()]> {
+        return self.timer.as_mut()?.read(wgpu_backend);
+    }
+
+    /// Seconds the last `render` waited for the swap chain to hand out a texture, which is not work
+    /// of the CPU.
+    pub fn surface_wait(&self) -> f32 {
+        return self.surface_wait;
+    }
+
+    /// Timestamps written by a pass of a timed frame, see `GpuTimer::writes`.
+    fn timestamps(&self, begin: Option<u32>, end: Option<u32>) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        return self.timer.as_ref()?.writes(begin, end);
+    }
+
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, logic: &Logic, ui: &Ui) {
+        let size = self.scene_size(wgpu_backend);
+        let viewport = self.scaling.viewport((wgpu_backend.config.width, wgpu_backend.config.height));
+
+        self.play.update(wgpu_backend, &logic.play, size, viewport.to_scene(logic.play.mouse_position, size));
+        self.fog_of_war.update(wgpu_backend, &logic.play, size);
+        self.tone_mapping_renderer.update(wgpu_backend, self.tone_mapping, self.scaling.upscaling, viewport.scale(size));
+
+        self.overlay.clear();
+        debug::draw(&mut self.overlay, &logic.debug, &logic.play.view_camera(), size, viewport);
+        ui::draw(&mut self.overlay, ui);
+        self.overlay.upload(wgpu_backend);
     }
 
     pub fn process_resize(&mut self, wgpu_backend: &WGPUBackend, logic: &Logic) {
-        self.play.process_resize(wgpu_backend, &logic.play);
+        let size = self.scene_size(wgpu_backend);
+
+        self.hdr_target = RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT);
+        self.post_processing.process_resize(wgpu_backend, size, &mut [&mut self.fog_of_war, &mut self.bloom]);
+        self.tone_mapping_renderer.set_input(wgpu_backend, self.post_processing.output(&self.hdr_target.view, Self::STAGES));
+
+        self.play.process_resize(wgpu_backend, &logic.play, size);
     }
 
-    pub fn render(&self, wgpu_backend: &WGPUBackend, logic: &Logic) {
+    /// Draws the frame. On a timed frame, the lighting is timed from the start of the first pass to
+    /// the end of the scene, then the post-processing until the end of the tone mapping, then the
+    /// overlays.
+    pub fn render(&mut self, wgpu_backend: &WGPUBackend, logic: &Logic) {
+        let start = Instant::now();
         let frame = wgpu_backend.surface.get_current_texture().expect("Failed to acquire next swap chain texture");
+        self.surface_wait = start.elapsed().as_secs_f32();
+
+        if let Some(timer) = &mut self.timer {
+            timer.begin_frame();
+        }
+
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = wgpu_backend.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: None,
         });
 
+        // The normals are cleared in a pass of their own: the GL backend clears the second
+        // attachment of a pass into the first one on GLES drivers
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: self.play.normal_view(),
+                resolve_target: None,
+                ops: Operations {
+                    // The normal of the floor
+                    load: LoadOp::Clear(wgpu::Color { r: 0.0, g: 0.0, b: 1.0, a: 0.0 }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: self.timestamps(Some(0), None),
+            occlusion_query_set: None,
+        });
+
+        if self.play.needs_compute() {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+
+            self.play.compute(&mut pass, &logic.play);
+        }
+
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[
+                    Some(RenderPassColorAttachment {
+                        view: &self.hdr_target.view,
+                        resolve_target: None,
+                        ops: Operations {
+                            // The albedo of the floor, multiplied by the lighting
+                            load: LoadOp::Clear(wgpu::Color::WHITE),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }),
+                    Some(RenderPassColorAttachment {
+                        view: self.play.normal_view(),
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            self.play.render_lit(&mut pass, &logic.play);
+        }
+
         {
             let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
+                    view: &self.hdr_target.view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 1.0,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
+                        load: LoadOp::Load,
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: self.timestamps(None, Some(1)),
                 occlusion_query_set: None,
             });
 
             self.play.render(&mut pass, &logic.play);
         }
 
+        self.post_processing.apply(wgpu_backend, &mut encoder, &self.hdr_target.view, &mut [&mut self.fog_of_war, &mut self.bloom]);
+
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: self.timestamps(None, Some(2)),
+                occlusion_query_set: None,
+            });
+
+            let viewport = self.scaling.viewport((wgpu_backend.config.width, wgpu_backend.config.height));
+            pass.set_viewport(viewport.x, viewport.y, viewport.width, viewport.height, 0.0, 1.0);
+
+            self.tone_mapping_renderer.render(&mut pass);
+        }
+
+        // Overlays, in window pixels
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: self.timestamps(None, Some(3)),
+                occlusion_query_set: None,
+            });
+
+            self.overlay.render(&mut pass);
+        }
+
+        if let Some(timer) = &self.timer {
+            timer.resolve(&mut encoder);
+        }
+
         wgpu_backend.queue.submit(Some(encoder.finish()));
         frame.present();
+
+        if let Some(timer) = &mut self.timer {
+            timer.submitted();
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,69 @@
+use glam::Vec2;
+
+use crate::{
+    logic::{
+        camera::Camera,
+        debug::{
+            DebugDraw,
+            DebugShape,
+        },
+    },
+    renderer::{
+        overlay::OverlayBatch,
+        scaling::Viewport,
+    },
+};
+
+const LINE_WIDTH: f32 = 1.0;
+const CIRCLE_SEGMENTS: usize = 32;
+/// Window pixels per pixel of the font.
+const TEXT_SCALE: f32 = 2.0;
+
+/// Draws the shapes of a `DebugDraw` into the overlay, in window pixels, so the lines stay thin
+/// and the text readable at any zoom and internal resolution. `camera` views the scene, rendered
+/// at `size` and shown in `viewport`.
+pub fn draw(overlay: &mut OverlayBatch, debug: &DebugDraw, camera: &Camera, size: (u32, u32), viewport: Viewport) {
+    let mvp = camera.mvp(size);
+
+    // Through the camera, then from the corners of the viewport to window pixels
+    let to_window = |position: Vec2| {
+        let clip = mvp.project_point3(position.extend(0.0));
+
+        return Vec2::new(
+            viewport.x + (clip.x + 1.0) / 2.0 * viewport.width,
+            viewport.y + (1.0 - clip.y) / 2.0 * viewport.height,
+        );
+    };
+
+    let pixels_per_unit = camera.zoom * viewport.scale(size);
+
+    for (shape, color) in &debug.shapes {
+        let color = *color;
+
+        match shape {
+            DebugShape::Line { a, b } => {
+                overlay.batch.line(to_window(*a), to_window(*b), LINE_WIDTH, color, 0.0);
+            }
+            DebugShape::Rect { min, max } => {
+                // Turned with the camera
+                let corners = [*min, Vec2::new(max.x, min.y), *max, Vec2::new(min.x, max.y)].map(to_window);
+
+                for index in 0..corners.len() {
+                    overlay.batch.line(corners[index], corners[(index + 1) % corners.len()], LINE_WIDTH, color, 0.0);
+                }
+            }
+            DebugShape::Circle { center, radius } => {
+                let center = to_window(*center);
+                let point = |index: usize| center + Vec2::from_angle(index as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU) * *radius * pixels_per_unit;
+
+                for index in 0..CIRCLE_SEGMENTS {
+                    overlay.batch.line(point(index), point(index + 1), LINE_WIDTH, color, 0.0);
+                }
+            }
+            DebugShape::Text { position, text } => {
+                // On whole pixels, keeping the glyphs sharp
+                overlay.font.draw(&mut overlay.batch, to_window(*position).round(), text, TEXT_SCALE, color, 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use glam::Vec2;
+
+use crate::font::{
+    self,
+    GLYPHS,
+    GLYPH_HEIGHT,
+    GLYPH_WIDTH,
+};
+use crate::renderer::sprite::{
+    AtlasRegion,
+    SpriteBatch,
+    TextureAtlas,
+};
+
+use crate::WGPUBackend;
+
+/// Glyphs of `font` packed into a texture atlas.
+pub struct BitmapFont {
+    glyphs: Vec<AtlasRegion>,
+}
+
+impl BitmapFont {
+    pub fn new(wgpu_backend: &WGPUBackend, atlas: &mut TextureAtlas) -> Self {
+        let glyphs = GLYPHS.iter()
+            .map(|columns| {
+                let pixels: Vec<u8> = (0..GLYPH_HEIGHT)
+                    .flat_map(|y| columns.iter().flat_map(move |column| if column >> y & 1 == 1 { [255; 4] } else { [0; 4] }))
+                    .collect();
+
+                atlas.add(wgpu_backend, GLYPH_WIDTH, GLYPH_HEIGHT, &pixels, None).expect("Font atlas too small")
+            })
+            .collect();
+
+        return Self {
+            glyphs,
+        };
+    }
+
+    /// Draws the text with its top left corner at `position`. The characters missing from the font
+    /// are drawn as `?`.
+    pub fn draw(&self, batch: &mut SpriteBatch, position: Vec2, text: &str, scale: f32, color: [u8; 4], depth: f32) {
+        let half_size = Vec2::new(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) / 2.0 * scale;
+
+        for (character, corner) in font::layout(text, position, scale) {
+            // Flipped, the sprites having their top row towards y
+            batch.sprite(corner + half_size, half_size * Vec2::new(1.0, -1.0), 0.0, self.glyphs[font::glyph_index(character)], color, depth);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use glam::Mat4;
+
+use crate::renderer::{
+    font::BitmapFont,
+    sprite::{
+        SpriteBatch,
+        TextureAtlas,
+    },
+};
+
+use crate::WGPUBackend;
+
+/// Shapes and text drawn over the final image in window pixels, by the debug drawing and the UI.
+pub struct OverlayBatch {
+    _atlas: TextureAtlas,
+    pub font: BitmapFont,
+
+    pub batch: SpriteBatch,
+}
+
+impl OverlayBatch {
+    const ATLAS_SIZE: u32 = 128;
+    const MAX_VERTICES: usize = 32768;
+
+    pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        let mut atlas = TextureAtlas::new(wgpu_backend, Self::ATLAS_SIZE);
+        let font = BitmapFont::new(wgpu_backend, &mut atlas);
+
+        let batch = SpriteBatch::overlay(wgpu_backend, &atlas, Self::MAX_VERTICES, wgpu_backend.config.format);
+
+        return Self {
+            _atlas: atlas,
+            font,
+
+            batch,
+        };
+    }
+
+    pub fn clear(&mut self) {
+        self.batch.clear();
+    }
+
+    /// Sends the shapes drawn since `clear` to the GPU, from window pixels to clip space.
+    pub fn upload(&mut self, wgpu_backend: &WGPUBackend) {
+        let (width, height) = (wgpu_backend.config.width as f32, wgpu_backend.config.height as f32);
+        let projection = Mat4::orthographic_rh(0.0, width, height, 0.0, -1.0, 1.0);
+
+        self.batch.upload(wgpu_backend, projection);
+    }
+
+    /// Draws over the whole window, without a viewport.
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        self.batch.render(pass);
+    }
+}
@@ -10,17 +10,48 @@ use bytemuck::{
 
 use wgpu::{
     BindGroupLayout,
+    BlendComponent,
+    BlendFactor,
+    BlendOperation,
+    BlendState,
     Face,
     RenderPipeline,
+    TextureFormat,
 };
 
+use crate::logic::play::Play;
 use crate::WGPUBackend;
 
+/// Format of the offscreen target the lighting is accumulated into before tone mapping.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Format of the normals of the sprites drawn before the lighting, cleared to the normal of the
+/// floor `(0, 0, 1)` with an alpha of 0.
+pub const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The lighting multiplies the target instead of replacing it. The target is cleared to white,
+/// the albedo of the floor, and the sprites drawn before the lighting leave their own albedo.
+pub const LIGHTING_BLEND: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::Dst,
+        dst_factor: BlendFactor::Zero,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::Zero,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// Colors are linear, like every color handed to the renderer. They multiply the texel of the
+/// texture atlas at `uv`, white for the shapes without an image.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ColorVertex {
     pub position: [f32; 2],
     pub color: [u8; 4],
+    pub uv: [f32; 2],
 }
 
 #[repr(C)]
@@ -29,6 +60,109 @@ pub struct SimpleVertex {
     pub position: [f32; 2],
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SkyUniform {
+    pub sun_direction: [f32; 2],
+    pub _padding: [f32; 2],
+    pub sun_radiance: [f32; 4],
+    pub ambient_radiance: [f32; 4],
+}
+
+pub const MAX_LIGHTS: usize = Play::MAX_LIGHTS;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct LightUniform {
+    pub position: [f32; 2],
+    pub falloff_parameters: [f32; 2],
+    pub radiance: [f32; 4],
+    pub falloff: u32,
+    pub _padding: [u32; 3],
+}
+
+/// Header of the light buffer, followed by `MAX_LIGHTS` `LightUniform`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct LightsHeader {
+    pub count: u32,
+    pub _padding: [u32; 3],
+}
+
+pub const MAX_OCCLUDERS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct OccluderUniform {
+    pub kind: u32,
+    pub count: u32,
+    pub _padding: [u32; 2],
+    pub color: [f32; 4],
+    pub points: [[f32; 2]; 8],
+}
+
+/// Header of the occluder buffer, followed by `MAX_OCCLUDERS` `OccluderUniform`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct OccludersHeader {
+    pub count: u32,
+    pub _padding: [u32; 3],
+}
+
+pub const MAX_SDF_SHAPES: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SdfShapeUniform {
+    pub kind: u32,
+    pub operation: u32,
+    pub count: u32,
+    pub _padding: u32,
+    /// Radius, smoothness and rotation.
+    pub parameters: [f32; 4],
+    pub points: [[f32; 2]; 8],
+}
+
+/// Header of the field buffer, followed by `MAX_SDF_SHAPES` `SdfShapeUniform`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SdfShapesHeader {
+    pub count: u32,
+    pub _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ToneMappingUniform {
+    pub mode: u32,
+    pub exposure: f32,
+    pub encode_srgb: u32,
+    pub upscaling: u32,
+    pub scale: f32,
+    pub _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct LightBufferUniform {
+    pub mode: u32,
+    pub _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FogOfWarUniform {
+    pub inverted_mvp: [f32; 16],
+    pub surface_size: [f32; 2],
+    pub enabled: u32,
+    pub _padding: u32,
+}
+
+/// Bits of the state of each tile in the fog of war buffer.
+pub const FOG_EXPLORED: u32 = 1;
+pub const FOG_VISIBLE: u32 = 2;
+pub const FOG_SOLID: u32 = 4;
+
 pub struct ColorPipeline {
     pub layout: BindGroupLayout,
     pub pipeline: RenderPipeline,
@@ -36,20 +170,69 @@ pub struct ColorPipeline {
 
 impl ColorPipeline {
     pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        return Self::create(wgpu_backend, HDR_FORMAT, false, "fs_main");
+    }
+
+    /// Variant drawing before the lighting, which also writes the normals of the sprites into a
+    /// second target of `NORMAL_FORMAT`, from the normal atlas bound at 3.
+    pub fn lit(wgpu_backend: &WGPUBackend) -> Self {
+        return Self::create(wgpu_backend, HDR_FORMAT, true, "fs_lit");
+    }
+
+    /// Variant drawing over the final image, into a target of `format` like the surface. The
+    /// colors are encoded to sRGB when the format doesn't do it, like the tone mapping does.
+    pub fn overlay(wgpu_backend: &WGPUBackend, format: wgpu::TextureFormat) -> Self {
+        let fragment_entry_point = if format.is_srgb() { "fs_main" } else { "fs_overlay_srgb" };
+
+        return Self::create(wgpu_backend, format, false, fragment_entry_point);
+    }
+
+    fn create(wgpu_backend: &WGPUBackend, format: wgpu::TextureFormat, lit: bool, fragment_entry_point: &str) -> Self {
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry { // Projection * View * Model Matrix
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry { // Texture Atlas
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry { // Atlas Sampler
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+
+        if lit {
+            entries.push(wgpu::BindGroupLayoutEntry { // Normal Atlas
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
+
         let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("BindGroupLayout for ColorPipeline"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry { // Projection * View * Model Matrix
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(64),
-                    },
-                    count: None,
-                },
-            ],
+            entries: &entries,
         });
 
         let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -78,10 +261,25 @@ impl ColorPipeline {
                     format: wgpu::VertexFormat::Unorm8x4,
                     offset: 2 * 4,
                     shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 3 * 4,
+                    shader_location: 2,
                 }
             ],
         };
 
+        let targets = [
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            // Normals are replaced, the transparent texels are discarded
+            Some(NORMAL_FORMAT.into()),
+        ];
+
         let render_pipeline = wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
@@ -92,13 +290,11 @@ impl ColorPipeline {
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu_backend.config.format.into())],
+                entry_point: fragment_entry_point,
+                targets: &targets[..if lit { 2 } else { 1 }],
             }),
-            primitive: wgpu::PrimitiveState {
-                cull_mode: Some(Face::Back),
-                ..Default::default()
-            },
+            // Shapes come in any winding
+            primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
@@ -123,7 +319,7 @@ impl RayMarchingPipeline {
             entries: &[
                 wgpu::BindGroupLayoutEntry { // Inverted MVP
                     binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -133,7 +329,7 @@ impl RayMarchingPipeline {
                 },
                 wgpu::BindGroupLayoutEntry { // Inverted MVP
                     binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -141,19 +337,19 @@ impl RayMarchingPipeline {
                     },
                     count: None,
                 },
-                wgpu::BindGroupLayoutEntry { // Inverted MVP
+                wgpu::BindGroupLayoutEntry { // Point lights
                     binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(8),
+                        min_binding_size: wgpu::BufferSize::new((mem::size_of::<LightsHeader>() + MAX_LIGHTS * mem::size_of::<LightUniform>()) as u64),
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry { // Tiles
                     binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
@@ -161,12 +357,149 @@ impl RayMarchingPipeline {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry { // Sun & Sky
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<SkyUniform>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Dynamic occluders
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new((mem::size_of::<OccludersHeader>() + MAX_OCCLUDERS * mem::size_of::<OccluderUniform>()) as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Distance field
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new((mem::size_of::<SdfShapesHeader>() + MAX_SDF_SHAPES * mem::size_of::<SdfShapeUniform>()) as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Surface normals
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("shaders/lighting.wgsl"), include_str!("shaders/ray_marching.wgsl")))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_size = mem::size_of::<SimpleVertex>();
+
+        let buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: vertex_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                }
+            ],
+        };
+
+        let render_pipeline = wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(LIGHTING_BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        return Self {
+            layout: bind_group_layout,
+            pipeline: render_pipeline,
+        };
+    }
+}
+
+pub struct ToneMappingPipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: RenderPipeline,
+}
+
+impl ToneMappingPipeline {
+    pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for ToneMappingPipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // HDR Texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // HDR Sampler
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Operator & Exposure
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<ToneMappingUniform>() as u64),
+                    },
+                    count: None,
+                },
             ],
         });
 
         let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/ray_marching.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/tone_mapping.wgsl"))),
         });
 
         let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -216,4 +549,515 @@ impl RayMarchingPipeline {
             pipeline: render_pipeline,
         };
     }
-}
\ No newline at end of file
+}
+
+/// Darkens the scene outside of the view, reading the HDR image and the mask of the view.
+pub struct FogOfWarPipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: RenderPipeline,
+}
+
+impl FogOfWarPipeline {
+    pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for FogOfWarPipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Scene Texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // View Mask Texture
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Inverted MVP & Surface Size
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<FogOfWarUniform>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Tile States
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/fog_of_war.wgsl"))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_size = mem::size_of::<SimpleVertex>();
+
+        let buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: vertex_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                }
+            ],
+        };
+
+        let render_pipeline = wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(HDR_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        return Self {
+            layout: bind_group_layout,
+            pipeline: render_pipeline,
+        };
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct BloomUniform {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    pub _padding: f32,
+}
+
+pub struct BloomPipeline {
+    pub layout: BindGroupLayout,
+
+    pub prefilter: RenderPipeline,
+    pub downsample: RenderPipeline,
+    pub upsample: RenderPipeline,
+    pub composite: RenderPipeline,
+}
+
+impl BloomPipeline {
+    pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for BloomPipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Source Texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Linear Sampler
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Threshold & Intensity
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<BloomUniform>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Scene Texture, only read by the composite
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/bloom.wgsl"))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+
+        return Self {
+            layout: bind_group_layout,
+
+            prefilter: Self::pass(wgpu_backend, &pipeline_layout, &shader, "fs_prefilter", None),
+            downsample: Self::pass(wgpu_backend, &pipeline_layout, &shader, "fs_downsample", None),
+            upsample: Self::pass(wgpu_backend, &pipeline_layout, &shader, "fs_upsample", Some(additive)),
+            composite: Self::pass(wgpu_backend, &pipeline_layout, &shader, "fs_composite", None),
+        };
+    }
+
+    fn pass(wgpu_backend: &WGPUBackend, pipeline_layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, entry_point: &str, blend: Option<wgpu::BlendState>) -> RenderPipeline {
+        let vertex_size = mem::size_of::<SimpleVertex>();
+
+        let buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: vertex_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                }
+            ],
+        };
+
+        return wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+    }
+}
+
+/// Evaluates the lighting into a storage texture, sharing the bind group of `RayMarchingPipeline`.
+pub struct LightingComputePipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: wgpu::ComputePipeline,
+}
+
+impl LightingComputePipeline {
+    pub fn new(wgpu_backend: &WGPUBackend, lighting_layout: &BindGroupLayout) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for LightingComputePipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Light Texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: HDR_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Mode
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<LightBufferUniform>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("shaders/lighting.wgsl"), include_str!("shaders/lighting_compute.wgsl")))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[lighting_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let compute_pipeline = wgpu_backend.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        return Self {
+            layout: bind_group_layout,
+            pipeline: compute_pipeline,
+        };
+    }
+}
+
+/// Upsamples the light texture of `LightingComputePipeline` to the target and draws the walls.
+pub struct LightingCompositePipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: RenderPipeline,
+}
+
+impl LightingCompositePipeline {
+    pub fn new(wgpu_backend: &WGPUBackend, lighting_layout: &BindGroupLayout) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for LightingCompositePipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Light Texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Mode
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<LightBufferUniform>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("shaders/lighting.wgsl"), include_str!("shaders/lighting_composite.wgsl")))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[lighting_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_size = mem::size_of::<SimpleVertex>();
+
+        let buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: vertex_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                }
+            ],
+        };
+
+        let render_pipeline = wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(LIGHTING_BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        return Self {
+            layout: bind_group_layout,
+            pipeline: render_pipeline,
+        };
+    }
+}
+
+/// One particle, drawn as an instance of a quad.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ParticleInstance {
+    pub position: [f32; 2],
+    pub size: f32,
+    pub emission: f32,
+    /// Linear RGB and opacity.
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ParticleUniform {
+    pub mvp: [f32; 16],
+    /// 1 to read the light from the texture of `LightingComputePipeline`, 0 to evaluate it.
+    pub sample_light_buffer: u32,
+    pub _padding: [u32; 3],
+}
+
+/// Draws the particles over the lit scene, lit by the light reaching them.
+pub struct ParticlePipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: RenderPipeline,
+}
+
+impl ParticlePipeline {
+    pub fn new(wgpu_backend: &WGPUBackend, lighting_layout: &BindGroupLayout, light_buffer_layout: &BindGroupLayout) -> Self {
+        let bind_group_layout = wgpu_backend.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BindGroupLayout for ParticlePipeline"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Projection * View * Model Matrix & Light Source
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<ParticleUniform>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = wgpu_backend.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("shaders/lighting.wgsl"), include_str!("shaders/particle.wgsl")))),
+        });
+
+        let pipeline_layout = wgpu_backend.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[lighting_layout, light_buffer_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let instance_size = mem::size_of::<ParticleInstance>();
+
+        let buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: instance_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32,
+                    offset: 2 * 4,
+                    shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32,
+                    offset: 3 * 4,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 4 * 4,
+                    shader_location: 3,
+                }
+            ],
+        };
+
+        let render_pipeline = wgpu_backend.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        return Self {
+            layout: bind_group_layout,
+            pipeline: render_pipeline,
+        };
+    }
+}
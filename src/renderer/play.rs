@@ -1,14 +1,31 @@
-use glam::{Mat4, Vec2, Vec3, Vec4};
+use bytemuck::Zeroable;
+
+use glam::{Mat4, Vec2, Vec3};
 
 use wgpu::util::DeviceExt;
 
 use crate::{
-    logic::play::Play,
-    renderer::pipeline,
+    logic::play::{
+        Play,
+        entity::Layer,
+        occluder::Shape,
+        sdf::{
+            Field,
+            Primitive,
+        },
+        sky::Sky,
+    },
+    renderer::{
+        pipeline,
+        target::RenderTarget,
+    },
 };
 
 use crate::WGPUBackend;
 
+pub mod entity;
+pub mod light_buffer;
+pub mod particle;
 pub mod world;
 
 pub struct PlayRenderer {
@@ -17,17 +34,30 @@ pub struct PlayRenderer {
     inverted_mvp_buffer: wgpu::Buffer,
     surface_configuration_buffer: wgpu::Buffer,
     point_light_buffer: wgpu::Buffer,
+    sky_buffer: wgpu::Buffer,
+    occluder_buffer: wgpu::Buffer,
+    world_buffer: wgpu::Buffer,
+    /// Revision of the tiles held by `world_buffer`.
+    world_revision: u64,
+    field_buffer: wgpu::Buffer,
+
+    /// Normals of the lit sprites, read by the lighting.
+    normal_target: RenderTarget,
 
     bind_group: wgpu::BindGroup,
 
+    light_buffer: light_buffer::LightBuffer,
+
     world: world::WorldRenderer,
+    entities: entity::EntityRenderer,
+    particles: particle::ParticleRenderer,
 }
 
 impl PlayRenderer {
-    pub fn new(wgpu_backend: &WGPUBackend, play: &Play) -> Self {
+    pub fn new(wgpu_backend: &WGPUBackend, play: &Play, (width, height): (u32, u32)) -> Self {
         let pipeline = pipeline::RayMarchingPipeline::new(wgpu_backend);
 
-        let inverted_mvp_data = Self::mvp(play, wgpu_backend.config.width, wgpu_backend.config.height).inverse();
+        let inverted_mvp_data = Self::mvp(play, width, height).inverse();
         let inverted_mvp_ref: &[f32; 16] = inverted_mvp_data.as_ref();
         let inverted_mvp_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
@@ -35,7 +65,7 @@ impl PlayRenderer {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let surface_configuration_data = [wgpu_backend.config.width as f32, wgpu_backend.config.height as f32];
+        let surface_configuration_data = [width as f32, height as f32];
         let surface_configuration_ref = surface_configuration_data.as_ref();
         let surface_configuration_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
@@ -43,14 +73,35 @@ impl PlayRenderer {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let point_light_data = play.mouse_position;
-        let point_light_ref = point_light_data.as_ref();
+        let cursor_position = play.view_camera().view_to_world(play.mouse_position, (width, height));
+        let point_light_data = Self::point_lights(play, cursor_position);
         let point_light_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(point_light_ref),
+            contents: &point_light_data,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sky_data = Self::sky(play);
+        let sky_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&sky_data),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let occluder_data = Self::occluders(play);
+        let occluder_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &occluder_data,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let field_data = Self::field(&play.world.field);
+        let field_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &field_data,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
         let world_ref = play.world.tiles.as_ref();
         let world_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
@@ -58,30 +109,15 @@ impl PlayRenderer {
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
-        let bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &pipeline.layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: inverted_mvp_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: surface_configuration_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: point_light_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: world_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        let normal_target = RenderTarget::new(wgpu_backend, (width, height), pipeline::NORMAL_FORMAT);
+
+        let bind_group = Self::bind_group(wgpu_backend, &pipeline.layout, [&inverted_mvp_buffer, &surface_configuration_buffer, &point_light_buffer, &world_buffer, &sky_buffer, &occluder_buffer, &field_buffer], &normal_target.view);
+
+        let light_buffer = light_buffer::LightBuffer::new(wgpu_backend, &pipeline.layout, light_buffer::LightingResolution::Full, (width, height));
 
         let world = world::WorldRenderer::new(wgpu_backend, &play.world);
+        let entities = entity::EntityRenderer::new(wgpu_backend, play, (width, height));
+        let particles = particle::ParticleRenderer::new(wgpu_backend, &pipeline.layout, &light_buffer, play, (width, height));
 
         return Self {
             pipeline,
@@ -89,46 +125,289 @@ impl PlayRenderer {
             inverted_mvp_buffer,
             surface_configuration_buffer,
             point_light_buffer,
+            sky_buffer,
+            occluder_buffer,
+            world_buffer,
+            world_revision: play.world.revision(),
+            field_buffer,
+
+            normal_target,
 
             bind_group,
+
+            light_buffer,
+
             world,
+            entities,
+            particles,
         };
     }
 
+    /// Bind group of the lighting, from its buffers in the order of their bindings and the
+    /// normals of the sprites.
+    fn bind_group(wgpu_backend: &WGPUBackend, layout: &wgpu::BindGroupLayout, buffers: [&wgpu::Buffer; 7], normals: &wgpu::TextureView) -> wgpu::BindGroup {
+        let mut entries: Vec<wgpu::BindGroupEntry> = buffers.iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+
+        entries.push(wgpu::BindGroupEntry {
+            binding: 7,
+            resource: wgpu::BindingResource::TextureView(normals),
+        });
+
+        return wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &entries,
+        });
+    }
+
     fn mvp(play: &Play, width: u32, height: u32) -> Mat4 {
-        return play.camera.mvp((width, height)) * Mat4::from_translation(Vec3::new((width as f32 - 1000.0) / 2.0, (height as f32 - 600.0) / 2.0, 0.0));
+        return play.view_camera().mvp((width, height));
+    }
+
+    fn sky(play: &Play) -> pipeline::SkyUniform {
+        let sun_direction = Sky::sun_direction(play.time_of_day);
+
+        let (sun_radiance, ambient_radiance) = match &play.world.sky {
+            Some(sky) => (sky.sun_radiance(play.time_of_day), sky.ambient_radiance(play.time_of_day)),
+            None => (Vec3::ZERO, Vec3::ZERO),
+        };
+
+        return pipeline::SkyUniform {
+            sun_direction: sun_direction.to_array(),
+            _padding: [0.0; 2],
+            sun_radiance: sun_radiance.extend(0.0).to_array(),
+            ambient_radiance: ambient_radiance.extend(0.0).to_array(),
+        };
     }
 
-    pub fn update(&mut self, wgpu_backend: &WGPUBackend, play: &Play) {
-        let x = 2.0 * play.mouse_position.x / wgpu_backend.config.width as f32 - 1.0;
-        let y = 1.0 - (2.0 * play.mouse_position.y) / wgpu_backend.config.height as f32;
-        let z = 1.0f32;
+    /// Content of the light buffer: the scene lights, with the cursor light at `cursor_position`.
+    fn point_lights(play: &Play, cursor_position: Vec2) -> Vec<u8> {
+        let mut lights = [pipeline::LightUniform::zeroed(); pipeline::MAX_LIGHTS];
+        let mut count = 0;
+
+        for (light, uniform) in play.scene_lights(cursor_position).into_iter().zip(lights.iter_mut()) {
+            *uniform = pipeline::LightUniform {
+                position: light.position.to_array(),
+                falloff_parameters: light.falloff.parameters().to_array(),
+                radiance: light.radiance().extend(0.0).to_array(),
+                falloff: light.falloff.kind(),
+                _padding: [0; 3],
+            };
+
+            count += 1;
+        }
 
-        let ray_nds = Vec3::new(x, y, z);
-        let ray_clip = Vec4::new(ray_nds.x, ray_nds.y, -1.0, 1.0);
+        let header = pipeline::LightsHeader {
+            count,
+            _padding: [0; 3],
+        };
 
-        let ray_eye = Self::mvp(play, wgpu_backend.config.width, wgpu_backend.config.height).inverse() * ray_clip;
+        let mut data = bytemuck::bytes_of(&header).to_vec();
+        data.extend_from_slice(bytemuck::cast_slice(&lights));
 
-        let point_light_data = Vec2::new(ray_eye.x, ray_eye.y);
-        let point_light_ref = point_light_data.as_ref();
-        wgpu_backend.queue.write_buffer(&self.point_light_buffer, 0, bytemuck::cast_slice(point_light_ref));
+        return data;
     }
 
-    pub fn process_resize(&mut self, wgpu_backend: &WGPUBackend, play: &Play) {
-        let inverted_mvp_data = Self::mvp(play, wgpu_backend.config.width, wgpu_backend.config.height).inverse();
+    /// Content of the occluder buffer, interpolated like the rest of the frame.
+    fn occluders(play: &Play) -> Vec<u8> {
+        let mut occluders = [pipeline::OccluderUniform::zeroed(); pipeline::MAX_OCCLUDERS];
+        let mut count = 0;
+
+        for (occluder, uniform) in play.view_occluders().iter().zip(occluders.iter_mut()) {
+            let mut points = [[0.0; 2]; Shape::MAX_POLYGON_VERTICES];
+
+            let point_count = match &occluder.shape {
+                Shape::Circle { center, radius } => {
+                    points[0] = center.to_array();
+                    points[1] = [*radius, 0.0];
+
+                    2
+                }
+                Shape::Aabb { min, max } => {
+                    points[0] = min.to_array();
+                    points[1] = max.to_array();
+
+                    2
+                }
+                Shape::Polygon(vertices) => {
+                    for (vertex, point) in vertices.iter().zip(points.iter_mut()) {
+                        *point = vertex.to_array();
+                    }
+
+                    vertices.len().min(Shape::MAX_POLYGON_VERTICES)
+                }
+            };
+
+            *uniform = pipeline::OccluderUniform {
+                kind: occluder.shape.kind(),
+                count: point_count as u32,
+                _padding: [0; 2],
+                color: occluder.color.extend(1.0).to_array(),
+                points,
+            };
+
+            count += 1;
+        }
+
+        let header = pipeline::OccludersHeader {
+            count,
+            _padding: [0; 3],
+        };
+
+        let mut data = bytemuck::bytes_of(&header).to_vec();
+        data.extend_from_slice(bytemuck::cast_slice(&occluders));
+
+        return data;
+    }
+
+    /// Content of the field buffer, the shapes of the level are uploaded once.
+    fn field(field: &Field) -> Vec<u8> {
+        let mut shapes = [pipeline::SdfShapeUniform::zeroed(); pipeline::MAX_SDF_SHAPES];
+
+        for (shape, uniform) in field.shapes.iter().zip(shapes.iter_mut()) {
+            let mut points = [[0.0; 2]; Primitive::MAX_POLYGON_VERTICES];
+
+            let (count, radius, rotation) = match &shape.primitive {
+                Primitive::Circle { center, radius } => {
+                    points[0] = center.to_array();
+
+                    (1, *radius, 0.0)
+                }
+                Primitive::Capsule { a, b, radius } => {
+                    points[0] = a.to_array();
+                    points[1] = b.to_array();
+
+                    (2, *radius, 0.0)
+                }
+                Primitive::Box { center, half_size, rotation } => {
+                    points[0] = center.to_array();
+                    points[1] = half_size.to_array();
+
+                    (2, 0.0, *rotation)
+                }
+                Primitive::Segment { a, b } => {
+                    points[0] = a.to_array();
+                    points[1] = b.to_array();
+
+                    (2, 0.0, 0.0)
+                }
+                Primitive::RoundedPolygon { vertices, radius } => {
+                    for (vertex, point) in vertices.iter().zip(points.iter_mut()) {
+                        *point = vertex.to_array();
+                    }
+
+                    (vertices.len().min(Primitive::MAX_POLYGON_VERTICES), *radius, 0.0)
+                }
+            };
+
+            let (operation, smoothness) = shape.operation.parameters();
+
+            *uniform = pipeline::SdfShapeUniform {
+                kind: shape.primitive.kind(),
+                operation,
+                count: count as u32,
+                _padding: 0,
+                parameters: [radius, smoothness, rotation, 0.0],
+                points,
+            };
+        }
+
+        let header = pipeline::SdfShapesHeader {
+            count: field.shapes.len().min(pipeline::MAX_SDF_SHAPES) as u32,
+            _padding: [0; 3],
+        };
+
+        let mut data = bytemuck::bytes_of(&header).to_vec();
+        data.extend_from_slice(bytemuck::cast_slice(&shapes));
+
+        return data;
+    }
+
+    /// `cursor` is the mouse position converted to pixels of the scene.
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, play: &Play, (width, height): (u32, u32), cursor: Vec2) {
+        let inverted_mvp_data = Self::mvp(play, width, height).inverse();
+        let inverted_mvp_ref: &[f32; 16] = inverted_mvp_data.as_ref();
+        wgpu_backend.queue.write_buffer(&self.inverted_mvp_buffer, 0, bytemuck::cast_slice(inverted_mvp_ref));
+
+        let cursor_position = play.view_camera().view_to_world(cursor, (width, height));
+
+        let point_light_data = Self::point_lights(play, cursor_position);
+        wgpu_backend.queue.write_buffer(&self.point_light_buffer, 0, &point_light_data);
+
+        let sky_data = Self::sky(play);
+        wgpu_backend.queue.write_buffer(&self.sky_buffer, 0, bytemuck::bytes_of(&sky_data));
+
+        let occluder_data = Self::occluders(play);
+        wgpu_backend.queue.write_buffer(&self.occluder_buffer, 0, &occluder_data);
+
+        if play.world.revision() != self.world_revision {
+            wgpu_backend.queue.write_buffer(&self.world_buffer, 0, bytemuck::cast_slice(play.world.tiles.as_ref()));
+            self.world_revision = play.world.revision();
+        }
+
+        self.entities.update(wgpu_backend, play, (width, height));
+        self.particles.update(wgpu_backend, play, (width, height), self.needs_compute());
+    }
+
+    pub fn process_resize(&mut self, wgpu_backend: &WGPUBackend, play: &Play, (width, height): (u32, u32)) {
+        let inverted_mvp_data = Self::mvp(play, width, height).inverse();
         let inverted_mvp_ref: &[f32; 16] = inverted_mvp_data.as_ref();
         wgpu_backend.queue.write_buffer(&self.inverted_mvp_buffer, 0, bytemuck::cast_slice(inverted_mvp_ref));
 
-        let surface_configuration_data = [wgpu_backend.config.width as f32, wgpu_backend.config.height as f32];
+        let surface_configuration_data = [width as f32, height as f32];
         let surface_configuration_ref = surface_configuration_data.as_ref();
         wgpu_backend.queue.write_buffer(&self.surface_configuration_buffer, 0, bytemuck::cast_slice(surface_configuration_ref));
+
+        self.light_buffer.process_resize(wgpu_backend, (width, height));
+
+        self.normal_target = RenderTarget::new(wgpu_backend, (width, height), pipeline::NORMAL_FORMAT);
+        self.bind_group = Self::bind_group(wgpu_backend, &self.pipeline.layout, [&self.inverted_mvp_buffer, &self.surface_configuration_buffer, &self.point_light_buffer, &self.world_buffer, &self.sky_buffer, &self.occluder_buffer, &self.field_buffer], &self.normal_target.view);
     }
 
+    pub fn set_lighting_resolution(&mut self, wgpu_backend: &WGPUBackend, resolution: light_buffer::LightingResolution, scene_size: (u32, u32)) {
+        self.light_buffer.set_resolution(wgpu_backend, resolution, scene_size);
+    }
+
+    /// Whether `compute` has to run before `render`.
+    pub fn needs_compute(&self) -> bool {
+        return self.light_buffer.resolution() != light_buffer::LightingResolution::Full;
+    }
 
+    pub fn compute<'a>(&'a self, pass: &mut wgpu::ComputePass<'a>, _play: &Play) {
+        self.light_buffer.compute(pass, &self.bind_group);
+    }
+
+    /// Target of the normals, the second attachment of the pass of `render_lit`.
+    pub fn normal_view(&self) -> &wgpu::TextureView {
+        return &self.normal_target.view;
+    }
+
+    /// Draws the albedo and the normals of the lit sprites, in a pass before `render`. The target
+    /// is cleared to white and the normals to `(0, 0, 1, 0)`.
+    pub fn render_lit<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, _play: &Play) {
+        self.entities.render(pass, Layer::Lit);
+    }
+
+    /// Lights what `render_lit` left in the target, see `pipeline::LIGHTING_BLEND`, and draws
+    /// what goes over the lighting.
     pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, _play: &Play) {
-        pass.set_pipeline(&self.pipeline.pipeline);
-        pass.set_bind_group(0, &self.bind_group, &[]);
+        if self.needs_compute() {
+            self.light_buffer.bind(pass, &self.bind_group);
+        } else {
+            pass.set_pipeline(&self.pipeline.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+        }
 
         self.world.render(pass);
+
+        self.entities.render(pass, Layer::Unlit);
+
+        self.particles.render(pass, &self.bind_group, &self.light_buffer);
     }
 }
\ No newline at end of file
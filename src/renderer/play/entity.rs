@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::{
+    logic::play::{
+        Play,
+        entity::{
+            Layer,
+            Sprite,
+        },
+    },
+    renderer::sprite::{
+        self,
+        AtlasRegion,
+        SpriteBatch,
+        TextureAtlas,
+    },
+};
+
+use crate::WGPUBackend;
+
+/// Pixels of a sprite, one string per row from the top. `#` is white, `+` light gray, `-` dark
+/// gray and `.` transparent, the color of the renderable tints them.
+fn sprite_pixels(sprite: Sprite) -> &'static [&'static str] {
+    return match sprite {
+        Sprite::Player => &[
+            "..####..",
+            ".######.",
+            ".#-##-#.",
+            ".######.",
+            "..####..",
+            ".+####+.",
+            "+######+",
+            "+######+",
+            ".######.",
+            "..#..#..",
+            "..#..#..",
+            ".##..##.",
+        ],
+        Sprite::Guard => &[
+            "..----..",
+            ".-####-.",
+            "-######-",
+            "-#-##-#-",
+            "-######-",
+            "-##--##-",
+            ".-####-.",
+            "..----..",
+        ],
+    };
+}
+
+/// Draws the renderable components at the transform of their entity, through one sprite batch
+/// per layer. The sprites get a bevelled normal map, shading their edges on the lit layer. Agents
+/// get a mark on the side they face.
+pub struct EntityRenderer {
+    atlas: TextureAtlas,
+    sprites: HashMap<Sprite, AtlasRegion>,
+
+    lit: SpriteBatch,
+    unlit: SpriteBatch,
+}
+
+impl EntityRenderer {
+    const ATLAS_SIZE: u32 = 256;
+
+    const MAX_RENDERABLES: usize = 256;
+    /// Two quads of two triangles at most per renderable, its body and its mark.
+    const VERTICES_PER_RENDERABLE: usize = 12;
+
+    const MARK_HALF_SIZE: f32 = 2.5;
+
+    pub fn new(wgpu_backend: &WGPUBackend, play: &Play, size: (u32, u32)) -> Self {
+        let mut atlas = TextureAtlas::new(wgpu_backend, Self::ATLAS_SIZE);
+
+        let sprites = [Sprite::Player, Sprite::Guard].into_iter()
+            .map(|sprite| {
+                let rows = sprite_pixels(sprite);
+
+                let pixels: Vec<u8> = rows.iter()
+                    .flat_map(|row| row.chars())
+                    .flat_map(|pixel| match pixel {
+                        '#' => [255, 255, 255, 255],
+                        '+' => [200, 200, 200, 255],
+                        '-' => [70, 70, 70, 255],
+                        _ => [0; 4],
+                    })
+                    .collect();
+
+                let (width, height) = (rows[0].len() as u32, rows.len() as u32);
+                let normals = sprite::bevel_normals(width, height, &pixels);
+
+                let region = atlas.add(wgpu_backend, width, height, &pixels, Some(&normals)).expect("Sprite atlas too small");
+
+                (sprite, region)
+            })
+            .collect();
+
+        let max_vertices = Self::MAX_RENDERABLES * Self::VERTICES_PER_RENDERABLE;
+
+        let lit = SpriteBatch::lit(wgpu_backend, &atlas, max_vertices);
+        let unlit = SpriteBatch::new(wgpu_backend, &atlas, max_vertices);
+
+        let mut entity_renderer = Self {
+            atlas,
+            sprites,
+
+            lit,
+            unlit,
+        };
+
+        entity_renderer.update(wgpu_backend, play, size);
+
+        return entity_renderer;
+    }
+
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, play: &Play, size: (u32, u32)) {
+        let entities = &play.entities;
+
+        self.lit.clear();
+        self.unlit.clear();
+
+        for (entity, renderable) in entities.renderables.iter().take(Self::MAX_RENDERABLES) {
+            let Some(transform) = entities.transforms.get(entity) else {
+                continue;
+            };
+
+            let position = transform.interpolated_position(play.interpolation);
+
+            let batch = match renderable.layer {
+                Layer::Lit => &mut self.lit,
+                Layer::Unlit => &mut self.unlit,
+            };
+
+            let region = renderable.sprite
+                .and_then(|sprite| self.sprites.get(&sprite).copied())
+                .unwrap_or(self.atlas.white());
+
+            batch.sprite(position, renderable.half_size, 0.0, region, renderable.color, renderable.depth);
+
+            if let Some(npc) = entities.npcs.get(entity) {
+                let mark = position + npc.facing * (renderable.half_size.max_element() + Self::MARK_HALF_SIZE);
+
+                batch.rect(mark - Self::MARK_HALF_SIZE, mark + Self::MARK_HALF_SIZE, [255; 4], renderable.depth);
+            }
+        }
+
+        let mvp = play.view_camera().mvp(size);
+
+        self.lit.upload(wgpu_backend, mvp);
+        self.unlit.upload(wgpu_backend, mvp);
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, layer: Layer) {
+        match layer {
+            Layer::Lit => self.lit.render(pass),
+            Layer::Unlit => self.unlit.render(pass),
+        }
+    }
+}
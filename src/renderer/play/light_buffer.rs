@@ -0,0 +1,190 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    logic::play::world::World,
+    renderer::pipeline,
+};
+
+use crate::WGPUBackend;
+
+/// Where the lighting is evaluated.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LightingResolution {
+    /// Once per pixel, in the fragment shader.
+    Full,
+    /// Once per texel of a buffer covering the scene, scaled by the given fraction.
+    Scaled(f32),
+    /// Once per texel of a buffer covering the world, with the given number of texels along each
+    /// side of a tile. The cost does not depend on the surface size.
+    PerTile(u32),
+}
+
+/// Low resolution light texture filled by a compute pass, then upsampled onto the target by a
+/// composite pass guided by the tiles.
+pub struct LightBuffer {
+    compute_pipeline: pipeline::LightingComputePipeline,
+    composite_pipeline: pipeline::LightingCompositePipeline,
+
+    resolution: LightingResolution,
+
+    mode_buffer: wgpu::Buffer,
+
+    size: (u32, u32),
+    compute_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup,
+}
+
+impl LightBuffer {
+    pub fn new(wgpu_backend: &WGPUBackend, lighting_layout: &wgpu::BindGroupLayout, resolution: LightingResolution, scene_size: (u32, u32)) -> Self {
+        let compute_pipeline = pipeline::LightingComputePipeline::new(wgpu_backend, lighting_layout);
+        let composite_pipeline = pipeline::LightingCompositePipeline::new(wgpu_backend, lighting_layout);
+
+        let mode_data = Self::uniform(resolution);
+        let mode_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&mode_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let size = Self::size(resolution, scene_size);
+        let (compute_bind_group, composite_bind_group) = Self::bind_groups(wgpu_backend, &compute_pipeline, &composite_pipeline, &mode_buffer, size);
+
+        return Self {
+            compute_pipeline,
+            composite_pipeline,
+
+            resolution,
+
+            mode_buffer,
+
+            size,
+            compute_bind_group,
+            composite_bind_group,
+        };
+    }
+
+    fn uniform(resolution: LightingResolution) -> pipeline::LightBufferUniform {
+        let mode = match resolution {
+            LightingResolution::PerTile(_) => 1,
+            _ => 0,
+        };
+
+        return pipeline::LightBufferUniform {
+            mode,
+            _padding: [0; 3],
+        };
+    }
+
+    fn size(resolution: LightingResolution, (scene_width, scene_height): (u32, u32)) -> (u32, u32) {
+        let (width, height) = match resolution {
+            LightingResolution::Full => (1, 1),
+            LightingResolution::Scaled(scale) => (
+                (scene_width as f32 * scale).ceil() as u32,
+                (scene_height as f32 * scale).ceil() as u32,
+            ),
+            LightingResolution::PerTile(texels) => (World::WIDTH as u32 * texels, World::HEIGHT as u32 * texels),
+        };
+
+        return (width.max(1), height.max(1));
+    }
+
+    fn bind_groups(wgpu_backend: &WGPUBackend, compute_pipeline: &pipeline::LightingComputePipeline, composite_pipeline: &pipeline::LightingCompositePipeline, mode_buffer: &wgpu::Buffer, (width, height): (u32, u32)) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        let texture = wgpu_backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: pipeline::HDR_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let compute_bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &compute_pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: mode_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let composite_bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &composite_pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: mode_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        return (compute_bind_group, composite_bind_group);
+    }
+
+    pub fn resolution(&self) -> LightingResolution {
+        return self.resolution;
+    }
+
+    /// Layout of `light_bind_group`.
+    pub fn light_layout(&self) -> &wgpu::BindGroupLayout {
+        return &self.composite_pipeline.layout;
+    }
+
+    /// Light texture and mode, for the passes reading the light after the composite.
+    pub fn light_bind_group(&self) -> &wgpu::BindGroup {
+        return &self.composite_bind_group;
+    }
+
+    pub fn set_resolution(&mut self, wgpu_backend: &WGPUBackend, resolution: LightingResolution, scene_size: (u32, u32)) {
+        self.resolution = resolution;
+
+        let mode_data = Self::uniform(resolution);
+        wgpu_backend.queue.write_buffer(&self.mode_buffer, 0, bytemuck::bytes_of(&mode_data));
+
+        self.process_resize(wgpu_backend, scene_size);
+    }
+
+    pub fn process_resize(&mut self, wgpu_backend: &WGPUBackend, scene_size: (u32, u32)) {
+        let size = Self::size(self.resolution, scene_size);
+
+        if size == self.size {
+            return;
+        }
+
+        self.size = size;
+        (self.compute_bind_group, self.composite_bind_group) = Self::bind_groups(wgpu_backend, &self.compute_pipeline, &self.composite_pipeline, &self.mode_buffer, size);
+    }
+
+    pub fn compute<'a>(&'a self, pass: &mut wgpu::ComputePass<'a>, lighting_bind_group: &'a wgpu::BindGroup) {
+        pass.set_pipeline(&self.compute_pipeline.pipeline);
+        pass.set_bind_group(0, lighting_bind_group, &[]);
+        pass.set_bind_group(1, &self.compute_bind_group, &[]);
+
+        pass.dispatch_workgroups(self.size.0.div_ceil(8), self.size.1.div_ceil(8), 1);
+    }
+
+    /// Binds the composite pipeline, the caller then draws a quad covering the target.
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, lighting_bind_group: &'a wgpu::BindGroup) {
+        pass.set_pipeline(&self.composite_pipeline.pipeline);
+        pass.set_bind_group(0, lighting_bind_group, &[]);
+        pass.set_bind_group(1, &self.composite_bind_group, &[]);
+    }
+}
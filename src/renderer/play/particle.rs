@@ -0,0 +1,121 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    logic::play::Play,
+    renderer::{
+        pipeline::{
+            self,
+            ParticleInstance,
+        },
+        play::light_buffer::LightBuffer,
+    },
+};
+
+use crate::WGPUBackend;
+
+/// Draws the particles as instanced quads after the lighting, reading the light reaching them
+/// from the light buffer when there is one, so they fall in the same shadows as the floor.
+pub struct ParticleRenderer {
+    pipeline: pipeline::ParticlePipeline,
+
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+}
+
+impl ParticleRenderer {
+    const MAX_PARTICLES: usize = 4096;
+
+    pub fn new(wgpu_backend: &WGPUBackend, lighting_layout: &wgpu::BindGroupLayout, light_buffer: &LightBuffer, play: &Play, size: (u32, u32)) -> Self {
+        let pipeline = pipeline::ParticlePipeline::new(wgpu_backend, lighting_layout, light_buffer.light_layout());
+
+        let uniform_data = Self::uniform(play, size, false);
+        let uniform_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&uniform_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let instance_buffer = wgpu_backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (Self::MAX_PARTICLES * std::mem::size_of::<ParticleInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        return Self {
+            pipeline,
+
+            uniform_buffer,
+            bind_group,
+
+            instance_buffer,
+            num_instances: 0,
+        };
+    }
+
+    fn uniform(play: &Play, size: (u32, u32), sample_light_buffer: bool) -> pipeline::ParticleUniform {
+        let mvp_data = play.view_camera().mvp(size);
+
+        return pipeline::ParticleUniform {
+            mvp: mvp_data.to_cols_array(),
+            sample_light_buffer: sample_light_buffer as u32,
+            _padding: [0; 3],
+        };
+    }
+
+    fn instances(play: &Play) -> Vec<ParticleInstance> {
+        let entities = &play.entities;
+
+        return entities.particles.iter()
+            .filter_map(|(entity, particle)| {
+                let position = entities.transforms.get(entity)?.interpolated_position(play.interpolation);
+
+                Some(ParticleInstance {
+                    position: position.to_array(),
+                    size: particle.size,
+                    emission: particle.emission,
+                    color: particle.color.extend(particle.opacity()).to_array(),
+                })
+            })
+            .take(Self::MAX_PARTICLES)
+            .collect();
+    }
+
+    /// `sample_light_buffer` tells whether the light buffer holds the lighting of the frame.
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, play: &Play, size: (u32, u32), sample_light_buffer: bool) {
+        let uniform_data = Self::uniform(play, size, sample_light_buffer);
+        wgpu_backend.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform_data));
+
+        let instances = Self::instances(play);
+        wgpu_backend.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        self.num_instances = instances.len() as u32;
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, lighting_bind_group: &'a wgpu::BindGroup, light_buffer: &'a LightBuffer) {
+        if self.num_instances == 0 {
+            return;
+        }
+
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, lighting_bind_group, &[]);
+        pass.set_bind_group(1, light_buffer.light_bind_group(), &[]);
+        pass.set_bind_group(2, &self.bind_group, &[]);
+
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..6, 0..self.num_instances);
+    }
+}
@@ -15,11 +15,12 @@ pub struct WorldRenderer {
 
 impl WorldRenderer {
     pub fn new(wgpu_backend: &WGPUBackend, _world: &World) -> Self {
-        let mut vertices = Vec::<SimpleVertex>::new();
-        vertices.push(SimpleVertex { position: [-1.0, 1.0] });
-        vertices.push(SimpleVertex { position: [-1.0, -1.0] });
-        vertices.push(SimpleVertex { position: [1.0, -1.0] });
-        vertices.push(SimpleVertex { position: [1.0, 1.0] });
+        let vertices = vec![
+            SimpleVertex { position: [-1.0, 1.0] },
+            SimpleVertex { position: [-1.0, -1.0] },
+            SimpleVertex { position: [1.0, -1.0] },
+            SimpleVertex { position: [1.0, 1.0] },
+        ];
 
         let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
 
@@ -0,0 +1,64 @@
+use crate::renderer::{
+    pipeline,
+    target::RenderTarget,
+};
+
+use crate::WGPUBackend;
+
+pub mod bloom;
+pub mod fog_of_war;
+
+/// Full screen effect applied to the HDR image between the lighting and the tone mapping.
+pub trait PostProcess {
+    fn process_resize(&mut self, wgpu_backend: &WGPUBackend, size: (u32, u32));
+
+    /// Records the passes reading `input` and writing the result into `output`, both HDR targets
+    /// of the scene size.
+    fn apply(&mut self, wgpu_backend: &WGPUBackend, encoder: &mut wgpu::CommandEncoder, input: &wgpu::TextureView, output: &wgpu::TextureView);
+}
+
+/// Runs post-processing stages one after another, ping-ponging between two HDR targets.
+pub struct PostProcessing {
+    targets: [RenderTarget; 2],
+}
+
+impl PostProcessing {
+    pub fn new(wgpu_backend: &WGPUBackend, size: (u32, u32)) -> Self {
+        return Self {
+            targets: Self::targets(wgpu_backend, size),
+        };
+    }
+
+    fn targets(wgpu_backend: &WGPUBackend, size: (u32, u32)) -> [RenderTarget; 2] {
+        return [
+            RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT),
+            RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT),
+        ];
+    }
+
+    pub fn process_resize(&mut self, wgpu_backend: &WGPUBackend, size: (u32, u32), stages: &mut [&mut dyn PostProcess]) {
+        self.targets = Self::targets(wgpu_backend, size);
+
+        for stage in stages {
+            stage.process_resize(wgpu_backend, size);
+        }
+    }
+
+    /// View holding the final image once `stage_count` stages ran on `input`.
+    pub fn output<'a>(&'a self, input: &'a wgpu::TextureView, stage_count: usize) -> &'a wgpu::TextureView {
+        if stage_count == 0 {
+            return input;
+        }
+
+        return &self.targets[(stage_count - 1) % 2].view;
+    }
+
+    pub fn apply(&self, wgpu_backend: &WGPUBackend, encoder: &mut wgpu::CommandEncoder, input: &wgpu::TextureView, stages: &mut [&mut dyn PostProcess]) {
+        for (i, stage) in stages.iter_mut().enumerate() {
+            let source = self.output(input, i);
+            let destination = &self.targets[i % 2].view;
+
+            stage.apply(wgpu_backend, encoder, source, destination);
+        }
+    }
+}
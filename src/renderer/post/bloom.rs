@@ -0,0 +1,214 @@
+use wgpu::util::DeviceExt;
+
+use crate::renderer::{
+    pipeline,
+    post::PostProcess,
+    screen_quad::ScreenQuad,
+    target::RenderTarget,
+};
+
+use crate::WGPUBackend;
+
+#[derive(Clone, Copy)]
+pub struct BloomSettings {
+    /// Brightness above which a pixel starts to glow.
+    pub threshold: f32,
+    /// Width of the soft transition around the threshold.
+    pub knee: f32,
+    /// Strength of the glow added back onto the scene, 0 disables the bloom.
+    pub intensity: f32,
+}
+
+impl BloomSettings {
+    pub fn new() -> Self {
+        return Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.6,
+        };
+    }
+}
+
+/// Bright-pass, blur pyramid and composite. The bright parts of the image are downsampled into
+/// a chain of half resolution targets, then upsampled back while accumulating every level.
+pub struct Bloom {
+    pub settings: BloomSettings,
+
+    pipeline: pipeline::BloomPipeline,
+
+    sampler: wgpu::Sampler,
+    bloom_buffer: wgpu::Buffer,
+
+    levels: Vec<RenderTarget>,
+    /// Built on the first frame after a resize, reading the input then every level.
+    bind_groups: Option<BindGroups>,
+
+    quad: ScreenQuad,
+}
+
+struct BindGroups {
+    input_id: wgpu::Id<wgpu::TextureView>,
+
+    input: wgpu::BindGroup,
+    levels: Vec<wgpu::BindGroup>,
+}
+
+impl Bloom {
+    const MAX_LEVELS: u32 = 6;
+
+    pub fn new(wgpu_backend: &WGPUBackend, size: (u32, u32), settings: BloomSettings) -> Self {
+        let pipeline = pipeline::BloomPipeline::new(wgpu_backend);
+
+        let sampler = wgpu_backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bloom_data = Self::uniform(&settings);
+        let bloom_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&bloom_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let levels = Self::levels(wgpu_backend, size);
+
+        let quad = ScreenQuad::new(wgpu_backend);
+
+        return Self {
+            settings,
+
+            pipeline,
+
+            sampler,
+            bloom_buffer,
+
+            levels,
+            bind_groups: None,
+
+            quad,
+        };
+    }
+
+    fn uniform(settings: &BloomSettings) -> pipeline::BloomUniform {
+        return pipeline::BloomUniform {
+            threshold: settings.threshold,
+            knee: settings.knee.max(0.0),
+            intensity: settings.intensity,
+            _padding: 0.0,
+        };
+    }
+
+    /// Targets of the pyramid, each one half the size of the previous one.
+    fn levels(wgpu_backend: &WGPUBackend, (width, height): (u32, u32)) -> Vec<RenderTarget> {
+        let mut levels = Vec::new();
+
+        let mut width = width / 2;
+        let mut height = height / 2;
+
+        while levels.len() < Self::MAX_LEVELS as usize && width >= 2 && height >= 2 {
+            levels.push(RenderTarget::new(wgpu_backend, (width, height), pipeline::HDR_FORMAT));
+
+            width /= 2;
+            height /= 2;
+        }
+
+        if levels.is_empty() {
+            levels.push(RenderTarget::new(wgpu_backend, (1, 1), pipeline::HDR_FORMAT));
+        }
+
+        return levels;
+    }
+
+    fn bind_group(&self, wgpu_backend: &WGPUBackend, source: &wgpu::TextureView, scene: &wgpu::TextureView) -> wgpu::BindGroup {
+        return wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.bloom_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                },
+            ],
+        });
+    }
+
+    /// Builds the bind groups of the passes again when the input or the levels changed.
+    fn update_bind_groups(&mut self, wgpu_backend: &WGPUBackend, input: &wgpu::TextureView) {
+        if self.bind_groups.as_ref().is_none_or(|bind_groups| bind_groups.input_id != input.global_id()) {
+            self.bind_groups = Some(BindGroups {
+                input_id: input.global_id(),
+
+                input: self.bind_group(wgpu_backend, input, input),
+                levels: self.levels.iter().map(|level| self.bind_group(wgpu_backend, &level.view, input)).collect(),
+            });
+        }
+    }
+
+    fn pass(&self, encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::RenderPipeline, bind_group: &wgpu::BindGroup, target: &wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+
+        self.quad.render(&mut pass);
+    }
+}
+
+impl PostProcess for Bloom {
+    fn process_resize(&mut self, wgpu_backend: &WGPUBackend, size: (u32, u32)) {
+        self.levels = Self::levels(wgpu_backend, size);
+        self.bind_groups = None;
+    }
+
+    fn apply(&mut self, wgpu_backend: &WGPUBackend, encoder: &mut wgpu::CommandEncoder, input: &wgpu::TextureView, output: &wgpu::TextureView) {
+        let bloom_data = Self::uniform(&self.settings);
+        wgpu_backend.queue.write_buffer(&self.bloom_buffer, 0, bytemuck::bytes_of(&bloom_data));
+
+        self.update_bind_groups(wgpu_backend, input);
+
+        let bind_groups = self.bind_groups.as_ref().unwrap();
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        self.pass(encoder, &self.pipeline.prefilter, &bind_groups.input, &self.levels[0].view, clear);
+
+        for i in 1..self.levels.len() {
+            self.pass(encoder, &self.pipeline.downsample, &bind_groups.levels[i - 1], &self.levels[i].view, clear);
+        }
+
+        for i in (1..self.levels.len()).rev() {
+            self.pass(encoder, &self.pipeline.upsample, &bind_groups.levels[i], &self.levels[i - 1].view, wgpu::LoadOp::Load);
+        }
+
+        self.pass(encoder, &self.pipeline.composite, &bind_groups.levels[0], output, clear);
+    }
+}
@@ -0,0 +1,227 @@
+use glam::{
+    IVec2,
+    Vec2,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    logic::play::{
+        Play,
+        world::World,
+    },
+    renderer::{
+        pipeline,
+        post::PostProcess,
+        screen_quad::ScreenQuad,
+        sprite::{
+            SpriteBatch,
+            TextureAtlas,
+        },
+        target::RenderTarget,
+    },
+};
+
+use crate::WGPUBackend;
+
+/// Hides what the player can't see. The view polygon is drawn into a mask, then the composite
+/// keeps the pixels inside it, dims and desaturates the explored tiles around it and blackens the
+/// rest. Runs first, so hidden lights don't bloom through the fog.
+pub struct FogOfWar {
+    pipeline: pipeline::FogOfWarPipeline,
+
+    fog_buffer: wgpu::Buffer,
+    tile_buffer: wgpu::Buffer,
+
+    /// Holds the white texel the view is drawn with.
+    _atlas: TextureAtlas,
+    view: SpriteBatch,
+
+    mask: RenderTarget,
+
+    quad: ScreenQuad,
+}
+
+impl FogOfWar {
+    /// Triangles of the view polygon drawn at most, one per edge.
+    const MAX_TRIANGLES: usize = 4096;
+    /// How far the outline of the view reaches into the shapes it stops on, so their lit edge
+    /// stays in view.
+    const REVEAL_DEPTH: f32 = 3.0;
+
+    pub fn new(wgpu_backend: &WGPUBackend, play: &Play, size: (u32, u32)) -> Self {
+        let pipeline = pipeline::FogOfWarPipeline::new(wgpu_backend);
+
+        let fog_data = Self::uniform(play, size);
+        let fog_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&fog_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let tile_data = Self::tiles(play);
+        let tile_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&tile_data),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let atlas = TextureAtlas::new(wgpu_backend, 1);
+        let view = SpriteBatch::new(wgpu_backend, &atlas, Self::MAX_TRIANGLES * 3);
+
+        let mask = RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT);
+
+        let quad = ScreenQuad::new(wgpu_backend);
+
+        return Self {
+            pipeline,
+
+            fog_buffer,
+            tile_buffer,
+
+            _atlas: atlas,
+            view,
+
+            mask,
+
+            quad,
+        };
+    }
+
+    fn uniform(play: &Play, size: (u32, u32)) -> pipeline::FogOfWarUniform {
+        return pipeline::FogOfWarUniform {
+            inverted_mvp: play.view_camera().mvp(size).inverse().to_cols_array(),
+            surface_size: [size.0 as f32, size.1 as f32],
+            enabled: play.fog_of_war.enabled as u32,
+            _padding: 0,
+        };
+    }
+
+    /// State of every tile, column major like the tiles.
+    fn tiles(play: &Play) -> Vec<u32> {
+        let mut tiles = vec![0; World::HEIGHT * World::WIDTH];
+
+        for (index, state) in tiles.iter_mut().enumerate() {
+            let tile = IVec2::new((index / World::HEIGHT) as i32, (index % World::HEIGHT) as i32);
+
+            if play.world.is_explored(tile) {
+                *state |= pipeline::FOG_EXPLORED;
+            }
+
+            if play.fog_of_war.is_visible(tile) {
+                *state |= pipeline::FOG_VISIBLE;
+            }
+
+            if play.world.is_solid(tile) {
+                *state |= pipeline::FOG_SOLID;
+            }
+        }
+
+        return tiles;
+    }
+
+    /// View polygon as a fan of triangles around its origin.
+    fn draw_view(&mut self, play: &Play) {
+        let view = &play.fog_of_war.view;
+
+        let outline: Vec<Vec2> = view.vertices.iter()
+            .map(|vertex| {
+                let offset = *vertex - view.origin;
+                let reveal = if offset.length() < view.radius { Self::REVEAL_DEPTH } else { 0.0 };
+
+                return *vertex + offset.normalize_or_zero() * reveal;
+            })
+            .collect();
+
+        self.view.clear();
+
+        for i in (0..outline.len()).take(Self::MAX_TRIANGLES) {
+            self.view.triangle(view.origin, outline[i], outline[(i + 1) % outline.len()], [255; 4], 0.0);
+        }
+    }
+
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, play: &Play, size: (u32, u32)) {
+        let fog_data = Self::uniform(play, size);
+        wgpu_backend.queue.write_buffer(&self.fog_buffer, 0, bytemuck::bytes_of(&fog_data));
+
+        if !play.fog_of_war.enabled {
+            return;
+        }
+
+        let tile_data = Self::tiles(play);
+        wgpu_backend.queue.write_buffer(&self.tile_buffer, 0, bytemuck::cast_slice(&tile_data));
+
+        self.draw_view(play);
+        self.view.upload(wgpu_backend, play.view_camera().mvp(size));
+    }
+}
+
+impl PostProcess for FogOfWar {
+    fn process_resize(&mut self, wgpu_backend: &WGPUBackend, size: (u32, u32)) {
+        self.mask = RenderTarget::new(wgpu_backend, size, pipeline::HDR_FORMAT);
+    }
+
+    fn apply(&mut self, wgpu_backend: &WGPUBackend, encoder: &mut wgpu::CommandEncoder, input: &wgpu::TextureView, output: &wgpu::TextureView) {
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.mask.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            self.view.render(&mut pass);
+        }
+
+        let bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.mask.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.fog_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.tile_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        self.quad.render(&mut pass);
+    }
+}
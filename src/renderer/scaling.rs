@@ -0,0 +1,102 @@
+use glam::Vec2;
+
+/// Size of the image the scene is rendered at, before being scaled to the window.
+#[derive(Clone, Copy, PartialEq)]
+pub enum InternalResolution {
+    /// Same size as the window, no scaling happens.
+    Window,
+    /// Fixed size, e.g. 320x180 for a pixel-art look.
+    Fixed { width: u32, height: u32 },
+}
+
+/// How the internal image is scaled to the window.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Upscaling {
+    /// Largest size keeping the aspect ratio, each window pixel taking its nearest texel.
+    Nearest,
+    /// Largest integer multiple of the internal size, centered with black bars around it. Falls
+    /// back to `Nearest` when the window is smaller than the internal size.
+    IntegerScale,
+    /// Largest size keeping the aspect ratio, filtered to keep the texels sharp while only
+    /// blending their edges.
+    SharpBilinear,
+}
+
+impl Upscaling {
+    /// Identifier of the filter on the GPU side.
+    pub fn filter(&self) -> u32 {
+        return match self {
+            Upscaling::Nearest | Upscaling::IntegerScale => 0,
+            Upscaling::SharpBilinear => 1,
+        };
+    }
+}
+
+/// Area of the window in pixels showing the scene.
+#[derive(Clone, Copy)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// Converts a position in window pixels to a position in pixels of the internal image.
+    pub fn to_scene(&self, position: Vec2, (width, height): (u32, u32)) -> Vec2 {
+        return Vec2::new(
+            (position.x - self.x) / self.width * width as f32,
+            (position.y - self.y) / self.height * height as f32,
+        );
+    }
+
+    /// Number of window pixels covered by one texel of the internal image.
+    pub fn scale(&self, (width, _height): (u32, u32)) -> f32 {
+        return self.width / width as f32;
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Scaling {
+    pub resolution: InternalResolution,
+    pub upscaling: Upscaling,
+}
+
+impl Scaling {
+    pub fn new() -> Self {
+        return Self {
+            resolution: InternalResolution::Window,
+            upscaling: Upscaling::Nearest,
+        };
+    }
+
+    pub fn scene_size(&self, window: (u32, u32)) -> (u32, u32) {
+        return match self.resolution {
+            InternalResolution::Window => window,
+            InternalResolution::Fixed { width, height } => (width.max(1), height.max(1)),
+        };
+    }
+
+    pub fn viewport(&self, (window_width, window_height): (u32, u32)) -> Viewport {
+        let (width, height) = self.scene_size((window_width, window_height));
+
+        let scale_x = window_width as f32 / width as f32;
+        let scale_y = window_height as f32 / height as f32;
+
+        let mut scale = scale_x.min(scale_y);
+
+        if self.upscaling == Upscaling::IntegerScale && scale >= 1.0 {
+            scale = scale.floor();
+        }
+
+        let viewport_width = width as f32 * scale;
+        let viewport_height = height as f32 * scale;
+
+        return Viewport {
+            x: ((window_width as f32 - viewport_width) / 2.0).floor(),
+            y: ((window_height as f32 - viewport_height) / 2.0).floor(),
+            width: viewport_width,
+            height: viewport_height,
+        };
+    }
+}
@@ -0,0 +1,49 @@
+use wgpu::util::DeviceExt;
+
+use crate::renderer::pipeline::SimpleVertex;
+
+use crate::WGPUBackend;
+
+/// Quad covering the whole viewport, used by the full screen passes.
+pub struct ScreenQuad {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl ScreenQuad {
+    pub fn new(wgpu_backend: &WGPUBackend) -> Self {
+        let vertices = vec![
+            SimpleVertex { position: [-1.0, 1.0] },
+            SimpleVertex { position: [-1.0, -1.0] },
+            SimpleVertex { position: [1.0, -1.0] },
+            SimpleVertex { position: [1.0, 1.0] },
+        ];
+
+        let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+        let vertex_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        return Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        };
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
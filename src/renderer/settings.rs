@@ -0,0 +1,160 @@
+use crate::config;
+use crate::renderer::{
+    play::light_buffer::LightingResolution,
+    scaling::{
+        InternalResolution,
+        Scaling,
+        Upscaling,
+    },
+    tone_mapping::ToneMapping,
+};
+
+/// Settings shipped with the game, used when the config file can't be read.
+const DEFAULT_SETTINGS: &str = include_str!("../../config/graphics.cfg");
+
+/// How the frame is rendered, applied to the `Renderer` at startup.
+///
+/// The config has one setting per line, `setting = value`, and `#` comments. Settings left out
+/// keep their default value.
+pub struct GraphicsSettings {
+    pub tone_mapping: ToneMapping,
+    pub lighting: LightingResolution,
+    pub scaling: Scaling,
+}
+
+impl GraphicsSettings {
+    pub fn new() -> Self {
+        return Self {
+            tone_mapping: ToneMapping::Aces,
+            lighting: LightingResolution::Scaled(0.5),
+            scaling: Scaling::new(),
+        };
+    }
+
+    pub fn builtin() -> Self {
+        return Self::parse(DEFAULT_SETTINGS).expect("Invalid default graphics settings");
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        return config::load(path, Self::parse);
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut settings = Self::new();
+
+        config::read_lines(source, "setting = value", |setting, value| {
+            let invalid = || format!("invalid {} `{}`", setting, value);
+
+            match setting {
+                "tone_mapping" => settings.tone_mapping = Self::tone_mapping(value).ok_or_else(invalid)?,
+                "lighting" => settings.lighting = Self::lighting(value).ok_or_else(invalid)?,
+                "resolution" => settings.scaling.resolution = Self::resolution(value).ok_or_else(invalid)?,
+                "upscaling" => settings.scaling.upscaling = Self::upscaling(value).ok_or_else(invalid)?,
+                _ => return Err(format!("unknown setting `{}`", setting)),
+            }
+
+            return Ok(());
+        })?;
+
+        return Ok(settings);
+    }
+
+    fn tone_mapping(value: &str) -> Option<ToneMapping> {
+        let tone_mapping = match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["aces"] => ToneMapping::Aces,
+            ["reinhard"] => ToneMapping::Reinhard,
+            ["exposure", exposure] => ToneMapping::Exposure { exposure: exposure.parse().ok()? },
+            _ => return None,
+        };
+
+        return Some(tone_mapping);
+    }
+
+    fn lighting(value: &str) -> Option<LightingResolution> {
+        let lighting = match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["full"] => LightingResolution::Full,
+            ["scaled", fraction] => LightingResolution::Scaled(fraction.parse().ok().filter(|fraction| *fraction > 0.0 && *fraction <= 1.0)?),
+            ["per_tile", texels] => LightingResolution::PerTile(texels.parse().ok().filter(|texels| *texels > 0)?),
+            _ => return None,
+        };
+
+        return Some(lighting);
+    }
+
+    fn resolution(value: &str) -> Option<InternalResolution> {
+        if value == "window" {
+            return Some(InternalResolution::Window);
+        }
+
+        let (width, height) = value.split_once('x')?;
+        let (width, height) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        return Some(InternalResolution::Fixed { width, height });
+    }
+
+    fn upscaling(value: &str) -> Option<Upscaling> {
+        return match value {
+            "nearest" => Some(Upscaling::Nearest),
+            "integer" => Some(Upscaling::IntegerScale),
+            "sharp_bilinear" => Some(Upscaling::SharpBilinear),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::{
+        play::light_buffer::LightingResolution,
+        scaling::{
+            InternalResolution,
+            Upscaling,
+        },
+        tone_mapping::ToneMapping,
+    };
+
+    use super::GraphicsSettings;
+
+    #[test]
+    fn parses_valid_lines() {
+        let settings = GraphicsSettings::parse("# Comment\n\ntone_mapping = exposure 1.5\n  lighting=per_tile 2   # trailing\nresolution = 320 x 180\nupscaling = integer\n").unwrap();
+
+        assert!(matches!(settings.tone_mapping, ToneMapping::Exposure { exposure } if exposure == 1.5));
+        assert!(settings.lighting == LightingResolution::PerTile(2));
+        assert!(settings.scaling.resolution == InternalResolution::Fixed { width: 320, height: 180 });
+        assert!(settings.scaling.upscaling == Upscaling::IntegerScale);
+
+        // Settings left out keep their default
+        let settings = GraphicsSettings::parse("upscaling = nearest").unwrap();
+
+        assert!(matches!(settings.tone_mapping, ToneMapping::Aces));
+        assert!(settings.lighting == LightingResolution::Scaled(0.5));
+
+        GraphicsSettings::builtin();
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert_eq!(GraphicsSettings::parse("upscaling = nearest\nbloom = on").err().unwrap(), "line 2: unknown setting `bloom`");
+        assert_eq!(GraphicsSettings::parse("tone_mapping aces").err().unwrap(), "line 1: expected `setting = value`");
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        for (source, error) in [
+            ("tone_mapping = exposure", "line 1: invalid tone_mapping `exposure`"),
+            ("tone_mapping = filmic", "line 1: invalid tone_mapping `filmic`"),
+            ("lighting = scaled 1.5", "line 1: invalid lighting `scaled 1.5`"),
+            ("lighting = per_tile 0", "line 1: invalid lighting `per_tile 0`"),
+            ("resolution = 0x180", "line 1: invalid resolution `0x180`"),
+            ("\nresolution = wide", "line 2: invalid resolution `wide`"),
+            ("upscaling = bicubic", "line 1: invalid upscaling `bicubic`"),
+        ] {
+            assert_eq!(GraphicsSettings::parse(source).err().unwrap(), error);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+struct VertexOutput {
+    @builtin(position) out_vertex_pos: vec4<f32>,
+    @location(0) out_vertex_uv: vec2<f32>
+}
+
+struct FragmentOutput {
+    @location(0) out_frag_color: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+
+    @location(0) in_vertex_position: vec2<f32>
+
+) -> VertexOutput {
+    var result: VertexOutput;
+
+    result.out_vertex_pos = vec4<f32> (in_vertex_position.x, in_vertex_position.y, 0.0, 1.0);
+    result.out_vertex_uv = vec2<f32> (in_vertex_position.x * 0.5 + 0.5, 0.5 - in_vertex_position.y * 0.5);
+
+    return result;
+}
+
+@group(0)
+@binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var linear_sampler: sampler;
+
+struct Bloom {
+    threshold: f32,
+    knee: f32,
+    intensity: f32
+}
+
+@group(0)
+@binding(2)
+var<uniform> bloom: Bloom;
+
+@group(0)
+@binding(3)
+var scene_texture: texture_2d<f32>;
+
+// Average of four bilinear taps, each one already averaging four texels of the source
+fn box_4 (uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32> (textureDimensions (source_texture));
+
+    var color = textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (-1.0, -1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (1.0, -1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (-1.0, 1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (1.0, 1.0)).rgb;
+
+    return color / 4.0;
+}
+
+// 3x3 tent filter, smooths the blocky look of the upsampled levels
+fn tent_9 (uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32> (textureDimensions (source_texture));
+
+    var color = textureSample (source_texture, linear_sampler, uv).rgb * 4.0;
+
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (-1.0, 0.0)).rgb * 2.0;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (1.0, 0.0)).rgb * 2.0;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (0.0, -1.0)).rgb * 2.0;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (0.0, 1.0)).rgb * 2.0;
+
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (-1.0, -1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (1.0, -1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (-1.0, 1.0)).rgb;
+    color += textureSample (source_texture, linear_sampler, uv + texel * vec2<f32> (1.0, 1.0)).rgb;
+
+    return color / 16.0;
+}
+
+// Keeps the part of the color above the threshold, with a quadratic knee to avoid a hard cut
+@fragment
+fn fs_prefilter(
+
+    @location(0) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let color = max (box_4 (in_vertex_uv), vec3<f32> (0.0));
+    let brightness = max (color.r, max (color.g, color.b));
+
+    var soft = clamp (brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+    soft = soft * soft / (4.0 * bloom.knee + 0.00001);
+
+    let contribution = max (soft, brightness - bloom.threshold) / max (brightness, 0.00001);
+
+    result.out_frag_color = vec4<f32> (color * contribution, 1.0);
+
+    return result;
+}
+
+@fragment
+fn fs_downsample(
+
+    @location(0) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    result.out_frag_color = vec4<f32> (box_4 (in_vertex_uv), 1.0);
+
+    return result;
+}
+
+@fragment
+fn fs_upsample(
+
+    @location(0) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    result.out_frag_color = vec4<f32> (tent_9 (in_vertex_uv), 1.0);
+
+    return result;
+}
+
+@fragment
+fn fs_composite(
+
+    @location(0) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let scene = textureSample (scene_texture, linear_sampler, in_vertex_uv);
+    let glow = tent_9 (in_vertex_uv) * bloom.intensity;
+
+    result.out_frag_color = vec4<f32> (scene.rgb + glow, scene.a);
+
+    return result;
+}
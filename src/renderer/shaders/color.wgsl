@@ -1,27 +1,49 @@
 struct VertexOutput {
     @builtin(position) out_vertex_pos: vec4<f32>,
-    @location(0) out_vertex_color: vec4<f32>
+    @location(0) out_vertex_color: vec4<f32>,
+    @location(1) out_vertex_uv: vec2<f32>
 }
 
 struct FragmentOutput {
     @location(0) out_frag_color: vec4<f32>
 }
 
+struct LitFragmentOutput {
+    @location(0) out_frag_color: vec4<f32>,
+    @location(1) out_frag_normal: vec4<f32>
+}
+
 @group(0)
 @binding(0)
 var<uniform> mvp: mat4x4<f32>;
 
+@group(0)
+@binding(1)
+var atlas: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var atlas_sampler: sampler;
+
+// Normals of the sprites, where the atlas has their colors. Encoded in `[0, 1]`, x to the right and y
+// up in the world
+@group(0)
+@binding(3)
+var normal_atlas: texture_2d<f32>;
+
 @vertex
 fn vs_main(
 
     @location(0) in_vertex_position: vec2<f32>,
     @location(1) in_vertex_color: vec4<f32>,
+    @location(2) in_vertex_uv: vec2<f32>,
 
 ) -> VertexOutput {
     var result: VertexOutput;
 
     result.out_vertex_pos = mvp * vec4<f32> (in_vertex_position.x, in_vertex_position.y, 0.0, 1.0);
     result.out_vertex_color = in_vertex_color;
+    result.out_vertex_uv = in_vertex_uv;
 
     return result;
 }
@@ -29,12 +51,73 @@ fn vs_main(
 @fragment
 fn fs_main(
 
-    @location(0) in_vertex_color: vec4<f32>
+    @location(0) in_vertex_color: vec4<f32>,
+    @location(1) in_vertex_uv: vec2<f32>
 
 ) -> FragmentOutput {
     var result: FragmentOutput;
 
-    result.out_frag_color = in_vertex_color;
+    let color = in_vertex_color * textureSample (atlas, atlas_sampler, in_vertex_uv);
+
+    // Fully transparent texels of the sprites leave no trace
+    if color.a == 0.0 {
+        discard;
+    }
+
+    result.out_frag_color = color;
 
     return result;
-}
\ No newline at end of file
+}
+
+// Like `linear_to_srgb` in `tone_mapping.wgsl`
+fn linear_to_srgb (color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow (color, vec3<f32> (1.0 / 2.4)) - 0.055;
+
+    return select (high, low, color <= vec3<f32> (0.0031308));
+}
+
+// `fs_main` drawing over the final image, on a surface that does not encode to sRGB on its own
+@fragment
+fn fs_overlay_srgb(
+
+    @location(0) in_vertex_color: vec4<f32>,
+    @location(1) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let color = in_vertex_color * textureSample (atlas, atlas_sampler, in_vertex_uv);
+
+    if color.a == 0.0 {
+        discard;
+    }
+
+    result.out_frag_color = vec4<f32> (linear_to_srgb (color.rgb), color.a);
+
+    return result;
+}
+
+// Albedo of a sprite drawn before the lighting, and its normal for the lighting to shade it with
+@fragment
+fn fs_lit(
+
+    @location(0) in_vertex_color: vec4<f32>,
+    @location(1) in_vertex_uv: vec2<f32>
+
+) -> LitFragmentOutput {
+    var result: LitFragmentOutput;
+
+    let color = in_vertex_color * textureSample (atlas, atlas_sampler, in_vertex_uv);
+
+    if color.a == 0.0 {
+        discard;
+    }
+
+    let normal = normalize (textureSample (normal_atlas, atlas_sampler, in_vertex_uv).xyz * 2.0 - 1.0);
+
+    result.out_frag_color = color;
+    result.out_frag_normal = vec4<f32> (normal, 1.0);
+
+    return result;
+}
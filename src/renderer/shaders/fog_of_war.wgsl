@@ -0,0 +1,111 @@
+struct VertexOutput {
+    @builtin(position) out_vertex_pos: vec4<f32>
+}
+
+struct FragmentOutput {
+    @location(0) out_frag_color: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+
+    @location(0) in_vertex_position: vec2<f32>
+
+) -> VertexOutput {
+    var result: VertexOutput;
+
+    result.out_vertex_pos = vec4<f32> (in_vertex_position.x, in_vertex_position.y, 0.0, 1.0);
+
+    return result;
+}
+
+@group(0)
+@binding(0)
+var scene_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var mask_texture: texture_2d<f32>;
+
+struct FogOfWar {
+    inverted_mvp: mat4x4<f32>,
+    surface_size: vec2<f32>,
+    enabled: u32
+}
+
+@group(0)
+@binding(2)
+var<uniform> fog: FogOfWar;
+
+// Column major like the tiles of the world, a combination of the bits below
+@group(0)
+@binding(3)
+var<storage, read> tiles: array<u32>;
+
+const fog_explored = 1u;
+const fog_visible = 2u;
+const fog_solid = 4u;
+
+// Look of the explored tiles out of view, the brightness being reached by the brightest lights
+const memory_brightness = 0.1;
+const memory_saturation = 0.2;
+
+fn world_position (frag_position: vec2<f32>) -> vec2<f32> {
+    let x = 2.0 * frag_position.x / fog.surface_size.x - 1.0;
+    let y = 1.0 - (2.0 * frag_position.y) / fog.surface_size.y;
+
+    return (fog.inverted_mvp * vec4<f32> (x, y, -1.0, 1.0)).xy;
+}
+
+// State of the tile under a position, nothing is known outside of the world
+fn tile_state (position: vec2<f32>) -> u32 {
+    let tile = vec2<i32> (floor (position / 20.0));
+
+    if tile.x < 0 || tile.x >= 50 || tile.y < 0 || tile.y >= 30 {
+        return 0u;
+    }
+
+    return tiles[tile.x * 30 + tile.y];
+}
+
+@fragment
+fn fs_main(
+
+    @builtin(position) in_frag_position: vec4<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let pixel = vec2<i32> (in_frag_position.xy);
+    let color = textureLoad (scene_texture, pixel, 0).rgb;
+
+    if fog.enabled == 0u {
+        result.out_frag_color = vec4<f32> (color, 1.0);
+
+        return result;
+    }
+
+    let state = tile_state (world_position (in_frag_position.xy));
+
+    // Walls are never inside the view polygon, which stops on their surface, so they are seen
+    // tile by tile
+    var visible = textureLoad (mask_texture, pixel, 0).r > 0.5;
+
+    if (state & fog_solid) != 0u {
+        visible = (state & fog_visible) != 0u;
+    }
+
+    if visible {
+        result.out_frag_color = vec4<f32> (color, 1.0);
+    } else if (state & fog_explored) != 0u {
+        let luminance = dot (color, vec3<f32> (0.2126, 0.7152, 0.0722));
+
+        let memory = mix (vec3<f32> (luminance), color, memory_saturation) / (1.0 + luminance);
+
+        result.out_frag_color = vec4<f32> (memory * memory_brightness, 1.0);
+    } else {
+        result.out_frag_color = vec4<f32> (0.0, 0.0, 0.0, 1.0);
+    }
+
+    return result;
+}
@@ -0,0 +1,604 @@
+// Bindings and functions shared by the lighting shaders, prepended to each of them
+
+@group(0)
+@binding(0)
+var<uniform> inverted_mvp: mat4x4<f32>;
+
+@group(0)
+@binding(1)
+var<uniform> surface_configuration: vec2<f32>;
+
+struct Light {
+    position: vec2<f32>,
+    falloff_parameters: vec2<f32>,
+    radiance: vec4<f32>,
+    falloff: u32
+}
+
+struct Lights {
+    count: u32,
+    lights: array<Light, 64>
+}
+
+@group(0)
+@binding(2)
+var<storage, read> point_lights: Lights;
+
+@group(0)
+@binding(3)
+var<storage, read> world: array<u32, 1500>;
+
+struct Sky {
+    sun_direction: vec2<f32>,
+    sun_radiance: vec4<f32>,
+    ambient_radiance: vec4<f32>
+}
+
+@group(0)
+@binding(4)
+var<uniform> sky: Sky;
+
+// 0 is a circle of center points[0] and radius points[1].x, 1 an AABB from points[0] to points[1],
+// 2 a convex counter-clockwise polygon of `count` points
+struct Occluder {
+    kind: u32,
+    count: u32,
+    color: vec4<f32>,
+    points: array<vec2<f32>, 8>
+}
+
+struct Occluders {
+    count: u32,
+    occluders: array<Occluder, 32>
+}
+
+@group(0)
+@binding(5)
+var<storage, read> dynamic_occluders: Occluders;
+
+// Distance kept before a tile, an occluder or the field when a ray stops on it, and past the edge of the
+// world when it leaves
+const surface_offset = 1.0;
+
+// Mirrors `Shape::entry`: distance along the ray at which it enters the occluder, -1 when it
+// doesn't or the origin is inside. Occluders are passed by index, their points can only be indexed
+// dynamically in the storage buffer
+fn occluder_entry (index: u32, ray_origin: vec2<f32>, ray_direction: vec2<f32>) -> f32 {
+    let occluder = &dynamic_occluders.occluders[index];
+
+    var entry = -1e30;
+    var exit = 1e30;
+
+    switch (*occluder).kind {
+        case 0u: {
+            let offset = ray_origin - (*occluder).points[0];
+            let radius = (*occluder).points[1].x;
+            let b = dot (offset, ray_direction);
+            let c = dot (offset, offset) - radius * radius;
+            let discriminant = b * b - c;
+
+            if discriminant < 0.0 {
+                return -1.0;
+            }
+
+            entry = -b - sqrt (discriminant);
+            exit = -b + sqrt (discriminant);
+        }
+        case 1u: {
+            for (var axis: i32 = 0; axis < 2; axis = axis + 1) {
+                let origin = ray_origin[axis];
+                let direction = ray_direction[axis];
+                let low = (*occluder).points[0][axis];
+                let high = (*occluder).points[1][axis];
+
+                if direction == 0.0 {
+                    if origin < low || origin > high {
+                        return -1.0;
+                    }
+
+                    continue;
+                }
+
+                let t_1 = (low - origin) / direction;
+                let t_2 = (high - origin) / direction;
+
+                entry = max (entry, min (t_1, t_2));
+                exit = min (exit, max (t_1, t_2));
+            }
+        }
+        default: {
+            // Clips the ray against the half plane inside each edge
+            let count = (*occluder).count;
+
+            // Less than 3 points make an empty polygon
+            if count < 3u {
+                return -1.0;
+            }
+
+            for (var i: u32 = 0u; i < count; i = i + 1u) {
+                let a = (*occluder).points[i];
+                let edge = (*occluder).points[(i + 1u) % count] - a;
+                let normal = vec2<f32> (edge.y, -edge.x);
+
+                let denominator = dot (normal, ray_direction);
+                let numerator = dot (normal, a - ray_origin);
+
+                if denominator == 0.0 {
+                    if numerator < 0.0 {
+                        return -1.0;
+                    }
+                } else if denominator > 0.0 {
+                    exit = min (exit, numerator / denominator);
+                } else {
+                    entry = max (entry, numerator / denominator);
+                }
+            }
+        }
+    }
+
+    if entry > exit || entry <= 0.0 {
+        return -1.0;
+    }
+
+    return entry;
+}
+
+// Distance along the ray to the closest occluder it enters, 1e30 when there is none
+fn closest_occluder (ray_origin: vec2<f32>, ray_direction: vec2<f32>) -> f32 {
+    var closest = 1e30;
+
+    for (var i: u32 = 0u; i < dynamic_occluders.count; i = i + 1u) {
+        let entry = occluder_entry (i, ray_origin, ray_direction);
+
+        if entry > 0.0 {
+            closest = min (closest, entry);
+        }
+    }
+
+    return closest;
+}
+
+// Analytic walls, merged in order into a single distance field. Kinds: 0 circle (points[0], radius),
+// 1 capsule (points[0] to points[1], radius), 2 box (center points[0], half size points[1],
+// rotation), 3 segment (points[0] to points[1]), 4 polygon of `count` points inflated by radius.
+// Operations: 0 union, 1 subtraction, 2 smooth union, 3 smooth subtraction. The parameters are the
+// radius, the smoothness and the rotation
+struct SdfShape {
+    kind: u32,
+    operation: u32,
+    count: u32,
+    parameters: vec4<f32>,
+    points: array<vec2<f32>, 8>
+}
+
+struct SdfShapes {
+    count: u32,
+    shapes: array<SdfShape, 32>
+}
+
+@group(0)
+@binding(6)
+var<storage, read> field: SdfShapes;
+
+// Normals of the sprites drawn before the lighting, one per pixel of the target. The alpha is 0 where
+// there is only the floor
+@group(0)
+@binding(7)
+var surface_normals: texture_2d<f32>;
+
+// Mirrors `Field`: distance under which a point is on the surface, and limits of the tracing
+const field_surface = 0.5;
+const field_empty = 1e30;
+const field_max_steps = 64;
+const field_max_distance = 1500.0;
+
+fn segment_distance (position: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = position - a;
+    let ba = b - a;
+    let h = clamp (dot (pa, ba) / max (dot (ba, ba), 1e-7), 0.0, 1.0);
+
+    return length (pa - ba * h);
+}
+
+// Mirrors `Primitive::distance`, shapes are passed by index like the occluders
+fn primitive_distance (index: u32, position: vec2<f32>) -> f32 {
+    let shape = &field.shapes[index];
+
+    switch (*shape).kind {
+        case 0u: {
+            return distance (position, (*shape).points[0]) - (*shape).parameters.x;
+        }
+        case 1u: {
+            return segment_distance (position, (*shape).points[0], (*shape).points[1]) - (*shape).parameters.x;
+        }
+        case 2u: {
+            let c = cos ((*shape).parameters.z);
+            let s = sin ((*shape).parameters.z);
+            let offset = position - (*shape).points[0];
+            let local = vec2<f32> (c * offset.x + s * offset.y, -s * offset.x + c * offset.y);
+            let d = abs (local) - (*shape).points[1];
+
+            return length (max (d, vec2<f32> (0.0))) + min (max (d.x, d.y), 0.0);
+        }
+        case 3u: {
+            return segment_distance (position, (*shape).points[0], (*shape).points[1]);
+        }
+        default: {
+            let count = (*shape).count;
+
+            if count == 0u {
+                return field_empty;
+            }
+
+            let first = position - (*shape).points[0];
+            var distance_squared = dot (first, first);
+            var sign = 1.0;
+
+            for (var i: u32 = 0u; i < count; i = i + 1u) {
+                let vertex = (*shape).points[i];
+                let previous = (*shape).points[(i + count - 1u) % count];
+
+                let edge = previous - vertex;
+                let w = position - vertex;
+                let b = w - edge * clamp (dot (w, edge) / max (dot (edge, edge), 1e-7), 0.0, 1.0);
+
+                distance_squared = min (distance_squared, dot (b, b));
+
+                let above = position.y >= vertex.y;
+                let below = position.y < previous.y;
+                let left = edge.x * w.y > edge.y * w.x;
+
+                if (above && below && left) || (!above && !below && !left) {
+                    sign = -sign;
+                }
+            }
+
+            return sign * sqrt (distance_squared) - (*shape).parameters.x;
+        }
+    }
+}
+
+// Mirrors `Field::distance`
+fn field_distance (position: vec2<f32>) -> f32 {
+    var result = field_empty;
+
+    for (var i: u32 = 0u; i < field.count; i = i + 1u) {
+        let primitive = primitive_distance (i, position);
+        let k = max (field.shapes[i].parameters.y, 1e-7);
+
+        switch field.shapes[i].operation {
+            case 1u: {
+                result = max (result, -primitive);
+            }
+            case 2u: {
+                let h = clamp (0.5 + 0.5 * (primitive - result) / k, 0.0, 1.0);
+                result = mix (primitive, result, h) - k * h * (1.0 - h);
+            }
+            case 3u: {
+                let h = clamp (0.5 - 0.5 * (result + primitive) / k, 0.0, 1.0);
+                result = mix (result, -primitive, h) + k * h * (1.0 - h);
+            }
+            default: {
+                result = min (result, primitive);
+            }
+        }
+    }
+
+    return result;
+}
+
+// Mirrors `Field::trace`: sphere traces the field, returns the distance to the surface or 1e30
+fn field_trace (ray_origin: vec2<f32>, ray_direction: vec2<f32>) -> f32 {
+    if field.count == 0u {
+        return 1e30;
+    }
+
+    var t = 0.0;
+
+    for (var i: i32 = 0; i < field_max_steps; i = i + 1) {
+        let distance = field_distance (ray_origin + ray_direction * t);
+
+        if distance < field_surface {
+            return t;
+        }
+
+        t += distance;
+
+        if t > field_max_distance {
+            return 1e30;
+        }
+    }
+
+    return 1e30;
+}
+
+// Distance along the ray to the closest occluder or surface of the field, 1e30 when there is none
+fn obstacle_distance (ray_origin: vec2<f32>, ray_direction: vec2<f32>) -> f32 {
+    return min (closest_occluder (ray_origin, ray_direction), field_trace (ray_origin, ray_direction));
+}
+
+fn occluder_contains (index: u32, position: vec2<f32>) -> bool {
+    let occluder = &dynamic_occluders.occluders[index];
+
+    switch (*occluder).kind {
+        case 0u: {
+            return distance (position, (*occluder).points[0]) < (*occluder).points[1].x;
+        }
+        case 1u: {
+            return all (position >= (*occluder).points[0]) && all (position < (*occluder).points[1]);
+        }
+        default: {
+            let count = (*occluder).count;
+
+            if count < 3u {
+                return false;
+            }
+
+            for (var i: u32 = 0u; i < count; i = i + 1u) {
+                let a = (*occluder).points[i];
+                let edge = (*occluder).points[(i + 1u) % count] - a;
+
+                if dot (vec2<f32> (edge.y, -edge.x), position - a) >= 0.0 {
+                    return false;
+                }
+            }
+
+            return true;
+        }
+    }
+}
+
+// Whether a tile blocks the light, tiles outside the grid are solid
+fn tile_solid (tile: vec2<i32>) -> bool {
+    if tile.x < 0 || tile.x >= 50 || tile.y < 0 || tile.y >= 30 {
+        return true;
+    }
+
+    return world[tile.x * 30 + tile.y] != 0u;
+}
+
+// Mirrors the walk of `World::raycast`: crosses the tiles from edge to edge, returns the distance to the first
+// solid tile within `max_distance`, or 1e30
+fn tile_distance (ray_origin: vec2<f32>, ray_direction: vec2<f32>, max_distance: f32) -> f32 {
+    let tile_size = 20.0;
+
+    var tile = vec2<i32> (floor (ray_origin / tile_size));
+
+    if tile_solid (tile) {
+        return 0.0;
+    }
+
+    let step = vec2<i32> (sign (ray_direction));
+
+    var next = vec2<f32> (1e30);
+    var delta = vec2<f32> (1e30);
+
+    for (var axis: i32 = 0; axis < 2; axis = axis + 1) {
+        if ray_direction[axis] != 0.0 {
+            let edge = f32 (tile[axis] + select (0, 1, step[axis] > 0)) * tile_size;
+
+            next[axis] = (edge - ray_origin[axis]) / ray_direction[axis];
+            delta[axis] = tile_size / abs (ray_direction[axis]);
+        }
+    }
+
+    // Enough edges to cross the whole grid
+    for (var i: i32 = 0; i < 82; i = i + 1) {
+        let axis = select (1, 0, next.x < next.y);
+        let distance = next[axis];
+
+        if distance > max_distance {
+            break;
+        }
+
+        tile[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        if tile_solid (tile) {
+            return distance;
+        }
+    }
+
+    return 1e30;
+}
+
+// Mirrors `World::line_of_sight`: a point sees itself unless it lies in a tile or the field
+fn ray_check (ray_origin: vec2<f32>, ray_target: vec2<f32>) -> bool {
+    let ray_length = length (ray_target - ray_origin);
+
+    if ray_length == 0.0 {
+        return !tile_solid (vec2<i32> (floor (ray_origin / 20.0))) && field_distance (ray_origin) >= field_surface;
+    }
+
+    let ray_direction = (ray_target - ray_origin) / ray_length;
+
+    if obstacle_distance (ray_origin, ray_direction) <= ray_length {
+        return false;
+    }
+
+    return tile_distance (ray_origin, ray_direction, ray_length) > ray_length;
+}
+
+// Mirrors `Falloff::attenuation`: 0 is linear, 1 is inverse square with a cutoff, 2 is a smooth window
+fn falloff (light: Light, distance: f32) -> f32 {
+    let radius = light.falloff_parameters.x;
+
+    switch light.falloff {
+        case 1u: {
+            let cutoff = light.falloff_parameters.y;
+
+            let attenuation = 1.0 / (1.0 + pow(distance / radius, 2.0));
+            let attenuation_cutoff = 1.0 / (1.0 + pow(cutoff / radius, 2.0));
+
+            return max((attenuation - attenuation_cutoff) / (1.0 - attenuation_cutoff), 0.0);
+        }
+        case 2u: {
+            let window = clamp(1.0 - pow(distance / radius, 4.0), 0.0, 1.0);
+
+            return window * window;
+        }
+        default: {
+            return max(1.0 - distance / radius, 0.0);
+        }
+    }
+}
+
+struct Hit {
+    hit: bool,
+    pos: vec2<f32>
+}
+
+fn ray_hit (ray_origin: vec2<f32>, ray_direction: vec2<f32>) -> Hit {
+    var result: Hit;
+
+    let obstacle_distance = obstacle_distance (ray_origin, ray_direction);
+    let tile_distance = tile_distance (ray_origin, ray_direction, obstacle_distance);
+
+    if tile_distance <= obstacle_distance {
+        // Tiles outside the grid let the ray leave the world, just past its edge
+        let beyond = ray_origin + ray_direction * (tile_distance + surface_offset);
+
+        if beyond.x < 0.0 || beyond.x >= 1000.0 || beyond.y < 0.0 || beyond.y >= 600.0 {
+            result.hit = false;
+            result.pos = beyond;
+
+            return result;
+        }
+
+        result.hit = true;
+        result.pos = ray_origin + ray_direction * max (tile_distance - surface_offset, 0.0);
+
+        return result;
+    }
+
+    result.hit = true;
+    result.pos = ray_origin + ray_direction * max (obstacle_distance - surface_offset, 0.0);
+
+    return result;
+}
+
+// Light coming from the sun at infinity, blocked by any tile between the pixel and the sun
+fn sun_light (ray_origin: vec2<f32>) -> vec3<f32> {
+    if all(sky.sun_radiance.rgb == vec3<f32> (0.0)) {
+        return vec3<f32> (0.0);
+    }
+
+    let hit = ray_hit (ray_origin, -sky.sun_direction);
+
+    if hit.hit {
+        return vec3<f32> (0.0);
+    }
+
+    return sky.sun_radiance.rgb;
+}
+
+// Ambient light, proportional to the share of the upper half circle that reaches the top edge of the world
+fn sky_light (ray_origin: vec2<f32>) -> vec3<f32> {
+    if all(sky.ambient_radiance.rgb == vec3<f32> (0.0)) {
+        return vec3<f32> (0.0);
+    }
+
+    var visibility = 0.0;
+
+    for (var i: i32 = 0; i < 8; i = i + 1) {
+        let angle = 3.141592 * (f32(i) + 0.5) / 8.0;
+        let ray_direction = vec2<f32> (cos(angle), sin(angle));
+
+        let hit = ray_hit (ray_origin, ray_direction);
+
+        if !hit.hit && hit.pos.y >= 600.0 {
+            visibility += 1.0 / 8.0;
+        }
+    }
+
+    return sky.ambient_radiance.rgb * visibility;
+}
+
+fn world_position (frag_position: vec2<f32>) -> vec2<f32> {
+    let x = 2.0 * frag_position.x / surface_configuration.x - 1.0;
+    let y = 1.0 - (2.0 * frag_position.y) / surface_configuration.y;
+
+    let ray_nds = vec3<f32> (x, y, 1.0);
+    let ray_clip = vec4<f32> (ray_nds.x, ray_nds.y, -1.0, 1.0);
+
+    return (inverted_mvp * ray_clip).xy;
+}
+
+// Color of the surfaces that are not lit: walls, the field, occluders and the outside of the world. The alpha
+// is 0 for an empty tile, whose color comes from `lighting`
+fn tile_color (ray_origin: vec2<f32>) -> vec4<f32> {
+    for (var i: u32 = 0u; i < dynamic_occluders.count; i = i + 1u) {
+        if occluder_contains (i, ray_origin) {
+            return vec4<f32> (dynamic_occluders.occluders[i].color.rgb, 1.0);
+        }
+    }
+
+    if ray_origin.x < 0.0 || ray_origin.x >= 1000.0 || ray_origin.y < 0.0 || ray_origin.y >= 600.0 {
+        return vec4<f32> (0.0, 0.0, 0.0, 1.0);
+    }
+
+    if field_distance (ray_origin) < field_surface {
+        return vec4<f32> (1.0, 0.0, 0.0, 1.0);
+    }
+
+    let tile_origin = vec2<i32> (i32(ray_origin.x / 20), i32(ray_origin.y / 20));
+
+    let tile = world[tile_origin.x * 30 + tile_origin.y];
+
+    if tile == 2 {
+        return vec4<f32> (4.0, 1.6, 0.4, 1.0);
+    }
+
+    if tile != 0 {
+        return vec4<f32> (1.0, 0.0, 0.0, 1.0);
+    }
+
+    return vec4<f32> (0.0);
+}
+
+// Normal at a pixel of the target, and whether a sprite set it
+fn surface_normal (frag_position: vec2<f32>) -> vec4<f32> {
+    return textureLoad (surface_normals, vec2<i32> (frag_position), 0);
+}
+
+// How much a surface turned towards `normal` is lit by a light in `direction`, relative to the floor.
+// The lights are taken 45 degrees above the plane, so the floor gets 1 and the sides of a sprite facing
+// the light up to √2
+fn shading (normal: vec3<f32>, direction: vec2<f32>) -> f32 {
+    let incoming = normalize (vec3<f32> (direction, 1.0));
+
+    return max (dot (normal, incoming), 0.0) / incoming.z;
+}
+
+// Light reaching a surface of the given normal, through the same occlusion as the floor
+fn surface_lighting (ray_origin: vec2<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var final_color = vec3<f32> (0.0);
+
+    for (var i: u32 = 0u; i < point_lights.count; i = i + 1u) {
+        let light = point_lights.lights[i];
+        let ray_direction = normalize (ray_origin - light.position);
+
+        let hit = ray_hit (ray_origin, ray_direction);
+
+        if hit.hit {
+            let distance_1 = length (ray_origin - hit.pos);
+
+            if ray_check(hit.pos, light.position) {
+                let distance_2 = length (hit.pos - light.position);
+
+                let t_1 = falloff (light, distance_1);
+                let t_2 = falloff (light, distance_2);
+
+                final_color += light.radiance.rgb * (t_1 * t_2) / 100.0f * shading (normal, -ray_direction);
+            }
+        }
+    }
+
+    final_color += sun_light (ray_origin) * shading (normal, -sky.sun_direction) + sky_light (ray_origin);
+
+    return final_color;
+}
+
+fn lighting (ray_origin: vec2<f32>) -> vec3<f32> {
+    return surface_lighting (ray_origin, vec3<f32> (0.0, 0.0, 1.0));
+}
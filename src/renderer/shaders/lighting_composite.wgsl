@@ -0,0 +1,118 @@
+struct VertexOutput {
+    @builtin(position) out_vertex_pos: vec4<f32>
+}
+
+struct FragmentOutput {
+    @location(0) out_frag_color: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+
+    @location(0) in_vertex_position: vec2<f32>
+
+) -> VertexOutput {
+    var result: VertexOutput;
+
+    result.out_vertex_pos = vec4<f32> (in_vertex_position.x, in_vertex_position.y, 0.0, 1.0);
+
+    return result;
+}
+
+struct LightBuffer {
+    mode: u32
+}
+
+@group(1)
+@binding(0)
+var light_texture: texture_2d<f32>;
+
+@group(1)
+@binding(1)
+var<uniform> light_buffer: LightBuffer;
+
+// Center of a texel of the light buffer in world space. Mode 0 covers the surface, mode 1 the world
+fn texel_world_position (texel: vec2<i32>) -> vec2<f32> {
+    let uv = (vec2<f32> (texel) + 0.5) / vec2<f32> (textureDimensions (light_texture));
+
+    if light_buffer.mode == 1u {
+        return uv * vec2<f32> (1000.0, 600.0);
+    }
+
+    return world_position (uv * surface_configuration);
+}
+
+// Continuous coordinate of a pixel in the light buffer, texel centers lying on integers
+fn light_texel_position (frag_position: vec2<f32>, ray_origin: vec2<f32>) -> vec2<f32> {
+    let dimensions = vec2<f32> (textureDimensions (light_texture));
+
+    if light_buffer.mode == 1u {
+        return ray_origin / vec2<f32> (1000.0, 600.0) * dimensions - 0.5;
+    }
+
+    return frag_position / surface_configuration * dimensions - 0.5;
+}
+
+// Bilinear upsampling that ignores the texels falling inside walls, so light never bleeds through
+// their edges
+@fragment
+fn fs_main(
+
+    @builtin(position) in_frag_position: vec4<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let ray_origin = world_position (in_frag_position.xy);
+
+    let color = tile_color (ray_origin);
+
+    if color.a > 0.0 {
+        result.out_frag_color = color;
+
+        return result;
+    }
+
+    // The buffer only holds the light of the floor, the few pixels of the sprites are lit in full
+    let normal = surface_normal (in_frag_position.xy);
+
+    if normal.a > 0.0 {
+        result.out_frag_color = vec4<f32> (surface_lighting (ray_origin, normal.xyz), 1.0);
+
+        return result;
+    }
+
+    let position = light_texel_position (in_frag_position.xy, ray_origin);
+    let base = floor (position);
+    let fraction = position - base;
+
+    let last = vec2<i32> (textureDimensions (light_texture)) - 1;
+
+    var total_color = vec3<f32> (0.0);
+    var total_weight = 0.0;
+
+    for (var i: i32 = 0; i < 4; i = i + 1) {
+        let offset = vec2<i32> (i % 2, i / 2);
+        let texel = clamp (vec2<i32> (base) + offset, vec2<i32> (0), last);
+
+        let bilinear = mix (1.0 - fraction.x, fraction.x, f32(offset.x)) * mix (1.0 - fraction.y, fraction.y, f32(offset.y));
+        let guide = select (0.0, 1.0, tile_color (texel_world_position (texel)).a == 0.0);
+
+        let weight = bilinear * guide;
+
+        total_color += textureLoad (light_texture, texel, 0).rgb * weight;
+        total_weight += weight;
+    }
+
+    if total_weight < 0.0001 {
+        let nearest = clamp (vec2<i32> (round (position)), vec2<i32> (0), last);
+
+        result.out_frag_color = vec4<f32> (textureLoad (light_texture, nearest, 0).rgb, 1.0);
+
+        return result;
+    }
+
+    result.out_frag_color = vec4<f32> (total_color / total_weight, 1.0);
+
+    return result;
+}
@@ -0,0 +1,46 @@
+struct LightBuffer {
+    mode: u32
+}
+
+@group(1)
+@binding(0)
+var light_texture: texture_storage_2d<rgba16float, write>;
+
+@group(1)
+@binding(1)
+var<uniform> light_buffer: LightBuffer;
+
+// Center of a texel of the light buffer in world space. Mode 0 covers the surface, mode 1 the world
+fn texel_world_position (texel: vec2<u32>) -> vec2<f32> {
+    let uv = (vec2<f32> (texel) + 0.5) / vec2<f32> (textureDimensions (light_texture));
+
+    if light_buffer.mode == 1u {
+        return uv * vec2<f32> (1000.0, 600.0);
+    }
+
+    return world_position (uv * surface_configuration);
+}
+
+@compute
+@workgroup_size(8, 8)
+fn cs_main(
+
+    @builtin(global_invocation_id) in_invocation_id: vec3<u32>
+
+) {
+    let dimensions = textureDimensions (light_texture);
+
+    if in_invocation_id.x >= dimensions.x || in_invocation_id.y >= dimensions.y {
+        return;
+    }
+
+    let ray_origin = texel_world_position (in_invocation_id.xy);
+
+    var color = vec4<f32> (0.0);
+
+    if tile_color (ray_origin).a == 0.0 {
+        color = vec4<f32> (lighting (ray_origin), 1.0);
+    }
+
+    textureStore (light_texture, in_invocation_id.xy, color);
+}
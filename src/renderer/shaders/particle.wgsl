@@ -0,0 +1,151 @@
+struct VertexOutput {
+    @builtin(position) out_vertex_pos: vec4<f32>,
+    @location(0) out_world_position: vec2<f32>,
+    @location(1) out_corner: vec2<f32>,
+    @location(2) out_color: vec4<f32>,
+    @location(3) out_emission: f32
+}
+
+struct FragmentOutput {
+    @location(0) out_frag_color: vec4<f32>
+}
+
+struct LightBuffer {
+    mode: u32
+}
+
+@group(1)
+@binding(0)
+var light_texture: texture_2d<f32>;
+
+@group(1)
+@binding(1)
+var<uniform> light_buffer: LightBuffer;
+
+struct Particles {
+    mvp: mat4x4<f32>,
+    sample_light_buffer: u32
+}
+
+@group(2)
+@binding(0)
+var<uniform> particles: Particles;
+
+@vertex
+fn vs_main(
+
+    @builtin(vertex_index) in_vertex_index: u32,
+    @location(0) in_particle_position: vec2<f32>,
+    @location(1) in_particle_size: f32,
+    @location(2) in_particle_emission: f32,
+    @location(3) in_particle_color: vec4<f32>
+
+) -> VertexOutput {
+    var result: VertexOutput;
+
+    // Two counter-clockwise triangles
+    var corners = array<vec2<f32>, 6> (
+        vec2<f32> (-1.0, -1.0),
+        vec2<f32> (1.0, -1.0),
+        vec2<f32> (1.0, 1.0),
+        vec2<f32> (1.0, 1.0),
+        vec2<f32> (-1.0, 1.0),
+        vec2<f32> (-1.0, -1.0),
+    );
+
+    let corner = corners[in_vertex_index];
+    let position = in_particle_position + corner * in_particle_size;
+
+    result.out_vertex_pos = particles.mvp * vec4<f32> (position.x, position.y, 0.0, 1.0);
+    result.out_world_position = position;
+    result.out_corner = corner;
+    result.out_color = in_particle_color;
+    result.out_emission = in_particle_emission;
+
+    return result;
+}
+
+// Center of a texel of the light buffer in world space. Mode 0 covers the surface, mode 1 the world
+fn texel_world_position (texel: vec2<i32>) -> vec2<f32> {
+    let uv = (vec2<f32> (texel) + 0.5) / vec2<f32> (textureDimensions (light_texture));
+
+    if light_buffer.mode == 1u {
+        return uv * vec2<f32> (1000.0, 600.0);
+    }
+
+    return world_position (uv * surface_configuration);
+}
+
+// Light of the buffer at a pixel, upsampled like in `lighting_composite.wgsl` so a particle gets the
+// light of the floor under it
+fn buffered_light (frag_position: vec2<f32>, ray_origin: vec2<f32>) -> vec3<f32> {
+    let dimensions = vec2<f32> (textureDimensions (light_texture));
+
+    var position = frag_position / surface_configuration * dimensions - 0.5;
+
+    if light_buffer.mode == 1u {
+        position = ray_origin / vec2<f32> (1000.0, 600.0) * dimensions - 0.5;
+    }
+
+    let base = floor (position);
+    let fraction = position - base;
+
+    let last = vec2<i32> (textureDimensions (light_texture)) - 1;
+
+    var total_color = vec3<f32> (0.0);
+    var total_weight = 0.0;
+
+    for (var i: i32 = 0; i < 4; i = i + 1) {
+        let offset = vec2<i32> (i % 2, i / 2);
+        let texel = clamp (vec2<i32> (base) + offset, vec2<i32> (0), last);
+
+        let bilinear = mix (1.0 - fraction.x, fraction.x, f32(offset.x)) * mix (1.0 - fraction.y, fraction.y, f32(offset.y));
+        let guide = select (0.0, 1.0, tile_color (texel_world_position (texel)).a == 0.0);
+
+        let weight = bilinear * guide;
+
+        total_color += textureLoad (light_texture, texel, 0).rgb * weight;
+        total_weight += weight;
+    }
+
+    if total_weight < 0.0001 {
+        return textureLoad (light_texture, clamp (vec2<i32> (round (position)), vec2<i32> (0), last), 0).rgb;
+    }
+
+    return total_color / total_weight;
+}
+
+// Round soft particle, lit by the light reaching the floor under it and glowing with its emission.
+// The walls hide the part of it over them
+@fragment
+fn fs_main(
+
+    @builtin(position) in_frag_position: vec4<f32>,
+    @location(0) in_world_position: vec2<f32>,
+    @location(1) in_corner: vec2<f32>,
+    @location(2) in_color: vec4<f32>,
+    @location(3) in_emission: f32
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let distance = length (in_corner);
+
+    if distance > 1.0 || tile_color (in_world_position).a > 0.0 {
+        discard;
+    }
+
+    var light = vec3<f32> (0.0);
+
+    if particles.sample_light_buffer == 1u {
+        light = buffered_light (in_frag_position.xy, in_world_position);
+    } else {
+        light = lighting (in_world_position);
+    }
+
+    let coverage = 1.0 - smoothstep (0.5, 1.0, distance);
+
+    result.out_frag_color = vec4<f32> (in_color.rgb * (light + in_emission), in_color.a * coverage);
+
+    return result;
+}
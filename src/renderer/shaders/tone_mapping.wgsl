@@ -0,0 +1,119 @@
+struct VertexOutput {
+    @builtin(position) out_vertex_pos: vec4<f32>,
+    @location(0) out_vertex_uv: vec2<f32>
+}
+
+struct FragmentOutput {
+    @location(0) out_frag_color: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+
+    @location(0) in_vertex_position: vec2<f32>
+
+) -> VertexOutput {
+    var result: VertexOutput;
+
+    result.out_vertex_pos = vec4<f32> (in_vertex_position.x, in_vertex_position.y, 0.0, 1.0);
+    result.out_vertex_uv = vec2<f32> (in_vertex_position.x * 0.5 + 0.5, 0.5 - in_vertex_position.y * 0.5);
+
+    return result;
+}
+
+@group(0)
+@binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var hdr_sampler: sampler;
+
+struct ToneMapping {
+    mode: u32,
+    exposure: f32,
+    encode_srgb: u32,
+    upscaling: u32,
+    scale: f32
+}
+
+@group(0)
+@binding(2)
+var<uniform> tone_mapping: ToneMapping;
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces (color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+
+    return clamp ((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32> (0.0), vec3<f32> (1.0));
+}
+
+fn reinhard (color: vec3<f32>) -> vec3<f32> {
+    return color / (vec3<f32> (1.0) + color);
+}
+
+fn exposure (color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32> (1.0) - exp (-color * tone_mapping.exposure);
+}
+
+// Texel centers for a nearest sampling, or sharp bilinear: blends only the band of window pixels
+// lying on the edge between two texels
+fn upscale_uv (uv: vec2<f32>) -> vec2<f32> {
+    let size = vec2<f32> (textureDimensions (hdr_texture));
+    let texel = uv * size;
+
+    if tone_mapping.upscaling == 1u {
+        let region = max (0.5 - 0.5 / tone_mapping.scale, 0.0);
+        let center_distance = fract (texel) - 0.5;
+        let offset = (center_distance - clamp (center_distance, vec2<f32> (-region), vec2<f32> (region))) * tone_mapping.scale + 0.5;
+
+        return (floor (texel) + offset) / size;
+    }
+
+    return (floor (texel) + 0.5) / size;
+}
+
+// Only used when the surface format does not encode to sRGB on its own
+fn linear_to_srgb (color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow (color, vec3<f32> (1.0 / 2.4)) - 0.055;
+
+    return select (high, low, color <= vec3<f32> (0.0031308));
+}
+
+@fragment
+fn fs_main(
+
+    @location(0) in_vertex_uv: vec2<f32>
+
+) -> FragmentOutput {
+    var result: FragmentOutput;
+
+    let color = max (textureSample (hdr_texture, hdr_sampler, upscale_uv (in_vertex_uv)).rgb, vec3<f32> (0.0));
+
+    var mapped: vec3<f32>;
+
+    switch tone_mapping.mode {
+        case 1u: {
+            mapped = reinhard (color);
+        }
+        case 2u: {
+            mapped = exposure (color);
+        }
+        default: {
+            mapped = aces (color);
+        }
+    }
+
+    if tone_mapping.encode_srgb == 1u {
+        mapped = linear_to_srgb (mapped);
+    }
+
+    result.out_frag_color = vec4<f32> (mapped, 1.0);
+
+    return result;
+}
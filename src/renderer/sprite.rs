@@ -0,0 +1,439 @@
+use std::ops::Range;
+
+use glam::{
+    Mat4,
+    UVec2,
+    Vec2,
+    Vec3,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::renderer::pipeline::{
+    self,
+    ColorVertex,
+};
+
+use crate::WGPUBackend;
+
+/// Rectangle of a texture atlas, in texture coordinates.
+#[derive(Clone, Copy)]
+pub struct AtlasRegion {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Square texture the images of the sprites are packed into, in rows. Its top left texel is
+/// white, for the shapes drawn without an image. A second texture holds the normal maps of the
+/// images at the same place, flat for the images without one.
+pub struct TextureAtlas {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    normal_texture: wgpu::Texture,
+    pub normal_view: wgpu::TextureView,
+
+    size: u32,
+    /// Where the next image goes, in the row starting at `cursor.y`.
+    cursor: UVec2,
+    row_height: u32,
+
+    white: AtlasRegion,
+}
+
+impl TextureAtlas {
+    /// Texels left empty between the images.
+    const PADDING: u32 = 1;
+
+    /// Encoded normal facing the viewer.
+    const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+
+    pub fn new(wgpu_backend: &WGPUBackend, size: u32) -> Self {
+        let texture = Self::texture(wgpu_backend, size, wgpu::TextureFormat::Rgba8UnormSrgb);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let normal_texture = Self::texture(wgpu_backend, size, wgpu::TextureFormat::Rgba8Unorm);
+        let normal_view = normal_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut atlas = Self {
+            texture,
+            view,
+            normal_texture,
+            normal_view,
+
+            size,
+            cursor: UVec2::ZERO,
+            row_height: 0,
+
+            white: AtlasRegion {
+                min: Vec2::ZERO,
+                max: Vec2::ZERO,
+            },
+        };
+
+        let white = atlas.add(wgpu_backend, 1, 1, &[255; 4], None).expect("Texture atlas too small");
+
+        // The center of the texel, so filtering never reaches its neighbours
+        let center = (white.min + white.max) / 2.0;
+        atlas.white = AtlasRegion { min: center, max: center };
+
+        return atlas;
+    }
+
+    fn texture(wgpu_backend: &WGPUBackend, size: u32, format: wgpu::TextureFormat) -> wgpu::Texture {
+        return wgpu_backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+    }
+
+    fn write(wgpu_backend: &WGPUBackend, texture: &wgpu::Texture, origin: UVec2, width: u32, height: u32, pixels: &[u8]) {
+        wgpu_backend.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: origin.x,
+                    y: origin.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Copies an image of `width` x `height` sRGB encoded RGBA pixels, rows from the top, into
+    /// the atlas, with its normal map if it has one, see `bevel_normals`. `None` when it doesn't
+    /// fit.
+    pub fn add(&mut self, wgpu_backend: &WGPUBackend, width: u32, height: u32, pixels: &[u8], normals: Option<&[u8]>) -> Option<AtlasRegion> {
+        if self.cursor.x + width > self.size {
+            self.cursor = UVec2::new(0, self.cursor.y + self.row_height + Self::PADDING);
+            self.row_height = 0;
+        }
+
+        if self.cursor.x + width > self.size || self.cursor.y + height > self.size {
+            return None;
+        }
+
+        let flat_normals = Self::FLAT_NORMAL.repeat((width * height) as usize);
+
+        Self::write(wgpu_backend, &self.texture, self.cursor, width, height, pixels);
+        Self::write(wgpu_backend, &self.normal_texture, self.cursor, width, height, normals.unwrap_or(&flat_normals));
+
+        let region = AtlasRegion {
+            min: self.cursor.as_vec2() / self.size as f32,
+            max: (self.cursor + UVec2::new(width, height)).as_vec2() / self.size as f32,
+        };
+
+        self.cursor.x += width + Self::PADDING;
+        self.row_height = self.row_height.max(height);
+
+        return Some(region);
+    }
+
+    pub fn white(&self) -> AtlasRegion {
+        return self.white;
+    }
+}
+
+/// Normal map making an image look bevelled: flat in its middle, turned outwards over the last
+/// `BEVEL` texels before its transparent edges. For the images drawn without a hand made normal
+/// map.
+pub fn bevel_normals(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    const BEVEL: i32 = 2;
+
+    let (width, height) = (width as i32, height as i32);
+
+    let is_opaque = |x: i32, y: i32| x >= 0 && x < width && y >= 0 && y < height && pixels[(4 * (y * width + x) + 3) as usize] > 0;
+
+    // Height of every texel, its distance to the closest transparent texel up to the bevel
+    let heights: Vec<f32> = (0..width * height)
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let mut closest = BEVEL as f32;
+
+            for dy in -BEVEL..=BEVEL {
+                for dx in -BEVEL..=BEVEL {
+                    if !is_opaque(x + dx, y + dy) {
+                        closest = closest.min(Vec2::new(dx as f32, dy as f32).length() - 0.5);
+                    }
+                }
+            }
+
+            return closest.max(0.0) / BEVEL as f32;
+        })
+        .collect();
+
+    let height_at = |x: i32, y: i32| if is_opaque(x, y) { heights[(y * width + x) as usize] } else { 0.0 };
+
+    return (0..width * height)
+        .flat_map(|index| {
+            let (x, y) = (index % width, index / width);
+
+            // Rows go down the image, y goes up in the world
+            let slope = Vec2::new(
+                height_at(x + 1, y) - height_at(x - 1, y),
+                height_at(x, y - 1) - height_at(x, y + 1),
+            ) / 2.0;
+
+            let normal = Vec3::new(-slope.x, -slope.y, 1.0 / BEVEL as f32).normalize();
+            let encoded = (normal * 0.5 + 0.5) * 255.0;
+
+            [encoded.x.round() as u8, encoded.y.round() as u8, encoded.z.round() as u8, 255]
+        })
+        .collect();
+}
+
+/// Collects sprites and flat shapes in world space during a frame, then draws them with a
+/// `ColorPipeline` in one call, sorted by depth: higher depths are drawn over lower ones, equal
+/// depths in the order they were pushed.
+pub struct SpriteBatch {
+    pipeline: pipeline::ColorPipeline,
+
+    mvp_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    vertex_buffer: wgpu::Buffer,
+    max_vertices: usize,
+    num_vertices: u32,
+
+    white: AtlasRegion,
+
+    vertices: Vec<ColorVertex>,
+    /// Depth and vertices of every shape pushed since `clear`.
+    shapes: Vec<(f32, Range<usize>)>,
+}
+
+impl SpriteBatch {
+    /// Segments of the circles.
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    pub fn new(wgpu_backend: &WGPUBackend, atlas: &TextureAtlas, max_vertices: usize) -> Self {
+        return Self::create(wgpu_backend, atlas, max_vertices, pipeline::ColorPipeline::new(wgpu_backend), false);
+    }
+
+    /// Batch drawn before the lighting, into the target and the normals, see `ColorPipeline::lit`.
+    pub fn lit(wgpu_backend: &WGPUBackend, atlas: &TextureAtlas, max_vertices: usize) -> Self {
+        return Self::create(wgpu_backend, atlas, max_vertices, pipeline::ColorPipeline::lit(wgpu_backend), true);
+    }
+
+    /// Batch drawn over the final image, into a target of `format`, see `ColorPipeline::overlay`.
+    pub fn overlay(wgpu_backend: &WGPUBackend, atlas: &TextureAtlas, max_vertices: usize, format: wgpu::TextureFormat) -> Self {
+        return Self::create(wgpu_backend, atlas, max_vertices, pipeline::ColorPipeline::overlay(wgpu_backend, format), false);
+    }
+
+    fn create(wgpu_backend: &WGPUBackend, atlas: &TextureAtlas, max_vertices: usize, pipeline: pipeline::ColorPipeline, lit: bool) -> Self {
+
+        let mvp_data = Mat4::IDENTITY;
+        let mvp_ref: &[f32; 16] = mvp_data.as_ref();
+        let mvp_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(mvp_ref),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = wgpu_backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&atlas.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ];
+
+        if lit {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&atlas.normal_view),
+            });
+        }
+
+        let bind_group = wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.layout,
+            entries: &entries,
+        });
+
+        let vertex_buffer = wgpu_backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (max_vertices * std::mem::size_of::<ColorVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        return Self {
+            pipeline,
+
+            mvp_buffer,
+            bind_group,
+
+            vertex_buffer,
+            max_vertices,
+            num_vertices: 0,
+
+            white: atlas.white(),
+
+            vertices: Vec::new(),
+            shapes: Vec::new(),
+        };
+    }
+
+    /// Forgets the shapes of the last frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.shapes.clear();
+    }
+
+    fn push(&mut self, depth: f32, vertices: &[ColorVertex]) {
+        let start = self.vertices.len();
+
+        self.vertices.extend_from_slice(vertices);
+        self.shapes.push((depth, start..self.vertices.len()));
+    }
+
+    fn vertex(&self, position: Vec2, color: [u8; 4]) -> ColorVertex {
+        return ColorVertex {
+            position: position.to_array(),
+            color,
+            uv: self.white.min.to_array(),
+        };
+    }
+
+    /// Image of an atlas region on a rectangle turned by `rotation` radians around its center,
+    /// multiplied by `color`.
+    pub fn sprite(&mut self, center: Vec2, half_size: Vec2, rotation: f32, region: AtlasRegion, color: [u8; 4], depth: f32) {
+        let rotation = Vec2::from_angle(rotation);
+
+        // Counter-clockwise from the bottom left corner, the images having their top row first
+        let corners = [
+            (Vec2::new(-1.0, -1.0), Vec2::new(region.min.x, region.max.y)),
+            (Vec2::new(1.0, -1.0), Vec2::new(region.max.x, region.max.y)),
+            (Vec2::new(1.0, 1.0), Vec2::new(region.max.x, region.min.y)),
+            (Vec2::new(-1.0, 1.0), Vec2::new(region.min.x, region.min.y)),
+        ]
+        .map(|(corner, uv)| ColorVertex {
+            position: (center + rotation.rotate(corner * half_size)).to_array(),
+            color,
+            uv: uv.to_array(),
+        });
+
+        self.push(depth, &[corners[0], corners[1], corners[2], corners[2], corners[3], corners[0]]);
+    }
+
+    pub fn triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: [u8; 4], depth: f32) {
+        let vertices = [self.vertex(a, color), self.vertex(b, color), self.vertex(c, color)];
+
+        self.push(depth, &vertices);
+    }
+
+    pub fn rect(&mut self, min: Vec2, max: Vec2, color: [u8; 4], depth: f32) {
+        let corners = [
+            self.vertex(min, color),
+            self.vertex(Vec2::new(max.x, min.y), color),
+            self.vertex(max, color),
+            self.vertex(Vec2::new(min.x, max.y), color),
+        ];
+
+        self.push(depth, &[corners[0], corners[1], corners[2], corners[2], corners[3], corners[0]]);
+    }
+
+    /// Segment drawn as a rectangle `width` wide.
+    pub fn line(&mut self, a: Vec2, b: Vec2, width: f32, color: [u8; 4], depth: f32) {
+        let side = (b - a).normalize_or_zero().perp() * width / 2.0;
+
+        let corners = [
+            self.vertex(a - side, color),
+            self.vertex(b - side, color),
+            self.vertex(b + side, color),
+            self.vertex(a + side, color),
+        ];
+
+        self.push(depth, &[corners[0], corners[1], corners[2], corners[2], corners[3], corners[0]]);
+    }
+
+    /// Filled disk, as a fan of `CIRCLE_SEGMENTS` triangles.
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: [u8; 4], depth: f32) {
+        let point = |i: usize| center + Vec2::from_angle(i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU) * radius;
+
+        let mut vertices = Vec::with_capacity(Self::CIRCLE_SEGMENTS * 3);
+
+        for i in 0..Self::CIRCLE_SEGMENTS {
+            vertices.push(self.vertex(center, color));
+            vertices.push(self.vertex(point(i), color));
+            vertices.push(self.vertex(point(i + 1), color));
+        }
+
+        self.push(depth, &vertices);
+    }
+
+    /// Sorts the shapes pushed since `clear` and copies them to the GPU, to be drawn through
+    /// `mvp`. The shapes past the capacity of the batch are dropped.
+    pub fn upload(&mut self, wgpu_backend: &WGPUBackend, mvp: Mat4) {
+        let mvp_ref: &[f32; 16] = mvp.as_ref();
+        wgpu_backend.queue.write_buffer(&self.mvp_buffer, 0, bytemuck::cast_slice(mvp_ref));
+
+        self.shapes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+
+        for (_, range) in &self.shapes {
+            if vertices.len() + range.len() > self.max_vertices {
+                break;
+            }
+
+            vertices.extend_from_slice(&self.vertices[range.clone()]);
+        }
+
+        wgpu_backend.queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        if self.num_vertices == 0 {
+            return;
+        }
+
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.num_vertices, 0..1);
+    }
+}
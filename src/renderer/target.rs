@@ -0,0 +1,35 @@
+use wgpu::TextureFormat;
+
+use crate::WGPUBackend;
+
+/// Offscreen texture a pass renders into and a later pass samples from.
+pub struct RenderTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl RenderTarget {
+    pub fn new(wgpu_backend: &WGPUBackend, (width, height): (u32, u32), format: TextureFormat) -> Self {
+        let texture = wgpu_backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        return Self {
+            texture,
+            view,
+        };
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::{
+    atomic::{
+        AtomicU8,
+        Ordering,
+    },
+    Arc,
+};
+
+use crate::WGPUBackend;
+
+enum State {
+    /// Ready to time the next frame.
+    Idle,
+    /// The frame being encoded writes the timestamps.
+    Recording,
+    /// The timestamps of a submitted frame are being read back, the frames are not timed
+    /// meanwhile.
+    Mapping,
+}
+
+/// Measures how long the GPU spends on each section of a frame with timestamp queries, only
+/// available when the device has `TIMESTAMP_QUERY`. The timestamps are written by the passes at
+/// the boundaries of the sections, and read back a few frames later without stalling.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+
+    state: State,
+    /// Set by the mapping callback to `MAPPED` once the readback buffer can be read, or to
+    /// `MAP_FAILED`.
+    mapping: Arc<AtomicU8>,
+    /// Readbacks that failed since the start, only the first one is reported.
+    failed_readbacks: u32,
+}
+
+impl GpuTimer {
+    /// Sections of the frame, each ending at the timestamp following its own.
+    pub const SECTIONS: [&'static str; 3] = ["lighting", "post", "ui"];
+    const TIMESTAMPS: u32 = Self::SECTIONS.len() as u32 + 1;
+
+    /// States of the mapping of the readback buffer.
+    const MAP_PENDING: u8 = 0;
+    const MAPPED: u8 = 1;
+    const MAP_FAILED: u8 = 2;
+
+    pub fn new(wgpu_backend: &WGPUBackend) -> Option<Self> {
+        if !wgpu_backend.device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = wgpu_backend.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU timer"),
+            ty: wgpu::QueryType::Timestamp,
+            count: Self::TIMESTAMPS,
+        });
+
+        let size = (Self::TIMESTAMPS * wgpu::QUERY_SIZE) as u64;
+
+        let resolve_buffer = wgpu_backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU timer resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = wgpu_backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU timer readback"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        return Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+
+            period: wgpu_backend.queue.get_timestamp_period(),
+
+            state: State::Idle,
+            mapping: Arc::new(AtomicU8::new(Self::MAP_PENDING)),
+            failed_readbacks: 0,
+        });
+    }
+
+    /// Starts encoding a frame, timed unless the last timestamps are still being read back.
+    pub fn begin_frame(&mut self) {
+        if let State::Idle = self.state {
+            self.state = State::Recording;
+        }
+    }
+
+    /// Timestamps for a render pass, writing `begin` when it starts and `end` when it ends. `None`
+    /// when the frame isn't timed.
+    pub fn writes(&self, begin: Option<u32>, end: Option<u32>) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if !matches!(self.state, State::Recording) {
+            return None;
+        }
+
+        return Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: begin,
+            end_of_pass_write_index: end,
+        });
+    }
+
+    /// Copies the timestamps of the frame where they can be read, after its last pass.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if !matches!(self.state, State::Recording) {
+            return;
+        }
+
+        encoder.resolve_query_set(&self.query_set, 0..Self::TIMESTAMPS, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, self.readback_buffer.size());
+    }
+
+    /// Starts reading the timestamps back, once the frame was submitted.
+    pub fn submitted(&mut self) {
+        if !matches!(self.state, State::Recording) {
+            return;
+        }
+
+        let mapping = self.mapping.clone();
+
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            mapping.store(if result.is_ok() { Self::MAPPED } else { Self::MAP_FAILED }, Ordering::Release);
+        });
+
+        self.state = State::Mapping;
+    }
+
+    /// Seconds spent on each of the `SECTIONS` by the last timed frame, once its timestamps are
+    /// back. `None` meanwhile, and when they can't be read back.
+    pub fn read(&mut self, wgpu_backend: &WGPUBackend) -> Option<[f32; Self::SECTIONS.len()]> {
+        if !matches!(self.state, State::Mapping) {
+            return None;
+        }
+
+        wgpu_backend.device.poll(wgpu::Maintain::Poll);
+
+        match self.mapping.swap(Self::MAP_PENDING, Ordering::Acquire) {
+            Self::MAPPED => {}
+            Self::MAP_FAILED => {
+                // A failed mapping leaves the buffer unmapped, the next frame can be timed
+                self.failed_readbacks += 1;
+                self.state = State::Idle;
+
+                if self.failed_readbacks == 1 {
+                    eprintln!("Failed to read the GPU timestamps back, the frames they timed are skipped");
+                }
+
+                return None;
+            }
+            _ => return None,
+        }
+
+        let timestamps: Vec<u64> = bytemuck::cast_slice(&self.readback_buffer.slice(..).get_mapped_range()).to_vec();
+        self.readback_buffer.unmap();
+        self.state = State::Idle;
+
+        // Some drivers write timestamps going backwards across passes
+        return Some(std::array::from_fn(|section| {
+            return timestamps[section + 1].saturating_sub(timestamps[section]) as f32 * self.period / 1e9;
+        }));
+    }
+}
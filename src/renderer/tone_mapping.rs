@@ -0,0 +1,123 @@
+use wgpu::util::DeviceExt;
+
+use crate::renderer::{
+    pipeline,
+    scaling::Upscaling,
+    screen_quad::ScreenQuad,
+};
+
+use crate::WGPUBackend;
+
+/// Operator mapping the unbounded HDR lighting to the displayable range. The result is encoded to
+/// sRGB, by the surface when its format is sRGB and by the pass otherwise. The same pass scales the
+/// scene to the window.
+#[derive(Clone, Copy)]
+pub enum ToneMapping {
+    Aces,
+    Reinhard,
+    Exposure { exposure: f32 },
+}
+
+impl ToneMapping {
+    /// `scale` is the number of window pixels covered by one texel of the scene.
+    fn uniform(&self, encode_srgb: bool, upscaling: Upscaling, scale: f32) -> pipeline::ToneMappingUniform {
+        let (mode, exposure) = match *self {
+            ToneMapping::Aces => (0, 1.0),
+            ToneMapping::Reinhard => (1, 1.0),
+            ToneMapping::Exposure { exposure } => (2, exposure),
+        };
+
+        return pipeline::ToneMappingUniform {
+            mode,
+            exposure,
+            encode_srgb: encode_srgb as u32,
+            upscaling: upscaling.filter(),
+            scale,
+            _padding: [0.0; 3],
+        };
+    }
+}
+
+pub struct ToneMappingRenderer {
+    pipeline: pipeline::ToneMappingPipeline,
+
+    sampler: wgpu::Sampler,
+    tone_mapping_buffer: wgpu::Buffer,
+
+    bind_group: wgpu::BindGroup,
+
+    quad: ScreenQuad,
+}
+
+impl ToneMappingRenderer {
+    pub fn new(wgpu_backend: &WGPUBackend, input: &wgpu::TextureView, tone_mapping: ToneMapping) -> Self {
+        let pipeline = pipeline::ToneMappingPipeline::new(wgpu_backend);
+
+        let sampler = wgpu_backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let tone_mapping_data = tone_mapping.uniform(!wgpu_backend.config.format.is_srgb(), Upscaling::Nearest, 1.0);
+        let tone_mapping_buffer = wgpu_backend.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&tone_mapping_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = Self::bind_group(wgpu_backend, &pipeline, input, &sampler, &tone_mapping_buffer);
+
+        let quad = ScreenQuad::new(wgpu_backend);
+
+        return Self {
+            pipeline,
+
+            sampler,
+            tone_mapping_buffer,
+
+            bind_group,
+
+            quad,
+        };
+    }
+
+    fn bind_group(wgpu_backend: &WGPUBackend, pipeline: &pipeline::ToneMappingPipeline, input: &wgpu::TextureView, sampler: &wgpu::Sampler, tone_mapping_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        return wgpu_backend.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: tone_mapping_buffer.as_entire_binding(),
+                },
+            ],
+        });
+    }
+
+    /// Points the pass to a new HDR texture, after the targets were recreated.
+    pub fn set_input(&mut self, wgpu_backend: &WGPUBackend, input: &wgpu::TextureView) {
+        self.bind_group = Self::bind_group(wgpu_backend, &self.pipeline, input, &self.sampler, &self.tone_mapping_buffer);
+    }
+
+    pub fn update(&mut self, wgpu_backend: &WGPUBackend, tone_mapping: ToneMapping, upscaling: Upscaling, scale: f32) {
+        let tone_mapping_data = tone_mapping.uniform(!wgpu_backend.config.format.is_srgb(), upscaling, scale);
+        wgpu_backend.queue.write_buffer(&self.tone_mapping_buffer, 0, bytemuck::bytes_of(&tone_mapping_data));
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+
+        self.quad.render(pass);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    renderer::overlay::OverlayBatch,
+    ui::{
+        Ui,
+        UiShape,
+    },
+};
+
+/// Over the debug shapes.
+const DEPTH: f32 = 2.0;
+
+/// Draws the shapes of a `Ui` into the overlay.
+pub fn draw(overlay: &mut OverlayBatch, ui: &Ui) {
+    // All at the same depth, the batch keeps the order the shapes were declared in
+    for shape in &ui.shapes {
+        match shape {
+            UiShape::Rect { min, max, color } => {
+                overlay.batch.rect(*min, *max, *color, DEPTH);
+            }
+            UiShape::Text { position, text, color } => {
+                overlay.font.draw(&mut overlay.batch, *position, text, Ui::TEXT_SCALE, *color, DEPTH);
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    ops::RangeInclusive,
+};
+
+use glam::Vec2;
+
+use crate::input::{
+    Button,
+    Input,
+    MouseButton,
+};
+use crate::font;
+
+/// Shape of the interface, in window pixels with y pointing down.
+pub enum UiShape {
+    Rect { min: Vec2, max: Vec2, color: [u8; 4] },
+    /// Text with its top left corner at `position`, drawn at `Ui::TEXT_SCALE`.
+    Text { position: Vec2, text: String, color: [u8; 4] },
+}
+
+/// Immediate mode interface: the widgets are declared again every frame between `begin` and
+/// `end`, and report how they were used right away. Widgets are laid out from top to bottom in
+/// panels, and identified by their label within their panel.
+pub struct Ui {
+    /// Shapes of the frame, in drawing order.
+    pub shapes: Vec<UiShape>,
+
+    window_size: Vec2,
+
+    cursor: Vec2,
+    pressed: bool,
+    released: bool,
+    held: bool,
+
+    /// Widget the left button went down on, until it is released.
+    active: Option<u64>,
+    /// Whether the cursor is over a panel of the frame.
+    hovered: bool,
+
+    /// Panel the widgets are added to, where the next widget goes and how wide it is.
+    panel: u64,
+    layout: Vec2,
+    width: f32,
+}
+
+impl Ui {
+    /// Window pixels per pixel of the font.
+    pub const TEXT_SCALE: f32 = 2.0;
+
+    const ROW_HEIGHT: f32 = 24.0;
+    /// Height of the bars of a graph, under its label.
+    const GRAPH_HEIGHT: f32 = 40.0;
+    const SPACING: f32 = 4.0;
+    const PADDING: f32 = 8.0;
+
+    const PANEL_COLOR: [u8; 4] = [2, 2, 4, 220];
+    const TITLE_COLOR: [u8; 4] = [12, 12, 24, 240];
+    const WIDGET_COLOR: [u8; 4] = [18, 18, 30, 255];
+    const HOVERED_COLOR: [u8; 4] = [35, 35, 60, 255];
+    const ACTIVE_COLOR: [u8; 4] = [60, 70, 140, 255];
+    const FILL_COLOR: [u8; 4] = [40, 48, 110, 255];
+    const GRAPH_COLOR: [u8; 4] = [70, 160, 100, 255];
+    const TEXT_COLOR: [u8; 4] = [255; 4];
+
+    pub fn new() -> Self {
+        return Self {
+            shapes: Vec::new(),
+
+            window_size: Vec2::ZERO,
+
+            cursor: Vec2::ZERO,
+            pressed: false,
+            released: false,
+            held: false,
+
+            active: None,
+            hovered: false,
+
+            panel: 0,
+            layout: Vec2::ZERO,
+            width: 0.0,
+        };
+    }
+
+    /// Starts a frame, forgetting the shapes of the last one.
+    pub fn begin(&mut self, input: &Input, window_size: (u32, u32)) {
+        let left = Button::Mouse(MouseButton::Left);
+
+        self.shapes.clear();
+        self.window_size = Vec2::new(window_size.0 as f32, window_size.1 as f32);
+
+        self.cursor = input.cursor_position;
+        self.pressed = input.is_pressed(left);
+        self.released = input.is_released(left);
+        self.held = input.is_held(left);
+
+        self.hovered = false;
+    }
+
+    /// Ends the frame. The mouse buttons and the wheel are hidden from the game while the cursor is
+    /// over the interface or a widget is held, so clicking a button doesn't also click the level.
+    pub fn end(&mut self, input: &mut Input) {
+        if self.hovered || self.active.is_some() {
+            for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+                input.consume(Button::Mouse(button));
+            }
+
+            input.consume(Button::WheelUp);
+            input.consume(Button::WheelDown);
+        }
+
+        if self.released || !self.held {
+            self.active = None;
+        }
+    }
+
+    pub fn window_size(&self) -> Vec2 {
+        return self.window_size;
+    }
+
+    fn id(&self, label: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.panel, label).hash(&mut hasher);
+
+        return hasher.finish();
+    }
+
+    fn is_hovered(&self, min: Vec2, max: Vec2) -> bool {
+        return self.cursor.cmpge(min).all() && self.cursor.cmplt(max).all();
+    }
+
+    /// Row of the current panel for the next widget, `height` pixels high.
+    fn allocate(&mut self, height: f32) -> (Vec2, Vec2) {
+        let min = self.layout;
+        let max = min + Vec2::new(self.width, height);
+
+        self.layout.y = max.y + Self::SPACING;
+
+        return (min, max);
+    }
+
+    /// Whether the widget was clicked: the button went down and up over it.
+    fn interact(&mut self, id: u64, min: Vec2, max: Vec2) -> bool {
+        let hovered = self.is_hovered(min, max);
+
+        if hovered && self.pressed {
+            self.active = Some(id);
+        }
+
+        return hovered && self.released && self.active == Some(id);
+    }
+
+    fn background(&self, id: u64, min: Vec2, max: Vec2) -> [u8; 4] {
+        if self.active == Some(id) {
+            return Self::ACTIVE_COLOR;
+        }
+
+        if self.is_hovered(min, max) {
+            return Self::HOVERED_COLOR;
+        }
+
+        return Self::WIDGET_COLOR;
+    }
+
+    pub fn rect(&mut self, min: Vec2, max: Vec2, color: [u8; 4]) {
+        self.shapes.push(UiShape::Rect { min, max, color });
+    }
+
+    /// Text anywhere in the window, outside of the panels.
+    pub fn text(&mut self, position: Vec2, text: &str, color: [u8; 4]) {
+        self.shapes.push(UiShape::Text { position, text: text.to_string(), color });
+    }
+
+    /// Text in a row going from `min` to `max`, vertically centered, and horizontally when
+    /// `centered`.
+    fn row_text(&mut self, min: Vec2, max: Vec2, text: &str, centered: bool) {
+        let size = font::measure(text, Self::TEXT_SCALE);
+
+        // The last row of the glyphs is below the baseline
+        let y = min.y + ((max.y - min.y) - (size.y - Self::TEXT_SCALE)) / 2.0;
+        let x = if centered { min.x + ((max.x - min.x) - size.x) / 2.0 } else { min.x + Self::PADDING };
+
+        self.text(Vec2::new(x, y).round(), text, Self::TEXT_COLOR);
+    }
+
+    /// Panel with a title bar, its top left corner at `position`, holding the widgets added by
+    /// `contents`. It grows to fit them.
+    pub fn panel(&mut self, title: &str, position: Vec2, width: f32, contents: impl FnOnce(&mut Self)) {
+        let background = self.shapes.len();
+        self.rect(position, position, Self::PANEL_COLOR);
+
+        let title_max = position + Vec2::new(width, Self::ROW_HEIGHT);
+        self.rect(position, title_max, Self::TITLE_COLOR);
+        self.row_text(position, title_max, title, false);
+
+        let mut hasher = DefaultHasher::new();
+        title.hash(&mut hasher);
+
+        self.panel = hasher.finish();
+        self.layout = position + Vec2::new(Self::PADDING, Self::ROW_HEIGHT + Self::PADDING);
+        self.width = width - 2.0 * Self::PADDING;
+
+        contents(self);
+
+        let max = Vec2::new(position.x + width, self.layout.y - Self::SPACING + Self::PADDING);
+        self.shapes[background] = UiShape::Rect { min: position, max, color: Self::PANEL_COLOR };
+
+        self.hovered |= self.is_hovered(position, max);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let (min, max) = self.allocate(Self::ROW_HEIGHT);
+
+        self.row_text(min, max, text, false);
+    }
+
+    /// Returns whether the button was clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        return self.selectable(label, false);
+    }
+
+    /// Button staying highlighted while `selected`, for picking one of several options.
+    pub fn selectable(&mut self, label: &str, selected: bool) -> bool {
+        let id = self.id(label);
+        let (min, max) = self.allocate(Self::ROW_HEIGHT);
+
+        let clicked = self.interact(id, min, max);
+
+        let color = if selected { Self::ACTIVE_COLOR } else { self.background(id, min, max) };
+        self.rect(min, max, color);
+        self.row_text(min, max, label, true);
+
+        return clicked;
+    }
+
+    /// Horizontal slider editing `value` within `range`, dragged with the left button. Returns
+    /// whether the value changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let id = self.id(label);
+        let (min, max) = self.allocate(Self::ROW_HEIGHT);
+
+        self.interact(id, min, max);
+
+        let (start, end) = (*range.start(), *range.end());
+        let mut changed = false;
+
+        if self.active == Some(id) {
+            let fraction = ((self.cursor.x - min.x) / (max.x - min.x)).clamp(0.0, 1.0);
+            let dragged = start + (end - start) * fraction;
+
+            changed = dragged != *value;
+            *value = dragged;
+        }
+
+        let fraction = ((*value - start) / (end - start)).clamp(0.0, 1.0);
+
+        let color = if self.background(id, min, max) == Self::WIDGET_COLOR { Self::FILL_COLOR } else { Self::ACTIVE_COLOR };
+        self.rect(min, max, Self::WIDGET_COLOR);
+        self.rect(min, Vec2::new(min.x + (max.x - min.x) * fraction, max.y), color);
+        self.row_text(min, max, &format!("{} {:.2}", label, value), false);
+
+        return changed;
+    }
+
+    /// Bar graph of `samples` under a label, oldest first, `capacity` of them filling its width.
+    /// The bars reach the top at `max_value`.
+    pub fn graph(&mut self, label: &str, samples: impl Iterator<Item = f32>, capacity: usize, max_value: f32) {
+        let (min, max) = self.allocate(Self::ROW_HEIGHT + Self::GRAPH_HEIGHT);
+        let bars = min.y + Self::ROW_HEIGHT;
+        let bar_width = (max.x - min.x) / capacity as f32;
+
+        self.row_text(min, Vec2::new(max.x, bars), label, false);
+        self.rect(Vec2::new(min.x, bars), max, Self::WIDGET_COLOR);
+
+        for (index, sample) in samples.take(capacity).enumerate() {
+            let x = min.x + index as f32 * bar_width;
+            let height = (sample / max_value).clamp(0.0, 1.0) * (max.y - bars);
+
+            self.rect(Vec2::new(x, max.y - height), Vec2::new(x + bar_width, max.y), Self::GRAPH_COLOR);
+        }
+    }
+}